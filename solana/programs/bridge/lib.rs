@@ -1,5 +1,9 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+};
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
 
 declare_id!("Bridge1111111111111111111111111111111111111");
 
@@ -10,11 +14,16 @@ pub mod miya_bridge {
     pub fn initialize_bridge(ctx: Context<InitializeBridge>) -> Result<()> {
         let bridge = &mut ctx.accounts.bridge;
         bridge.authority = ctx.accounts.authority.key();
+        bridge.pending_authority = None;
         bridge.is_active = true;
         bridge.supported_chain_count = 0;
         bridge.total_locked_tokens = 0;
         bridge.total_released_tokens = 0;
         
+        // Store the bump so later instructions can verify and sign for the bridge PDA
+        let (_, bump) = Pubkey::find_program_address(&[b"miya_bridge".as_ref()], ctx.program_id);
+        bridge.bump = bump;
+        
         msg!("Bridge initialized");
         
         Ok(())
@@ -58,7 +67,7 @@ pub mod miya_bridge {
     }
 
     pub fn update_chain_status(
-        ctx: Context<ManageSupportedChain>,
+        ctx: Context<UpdateSupportedChain>,
         is_active: bool,
     ) -> Result<()> {
         let bridge = &ctx.accounts.bridge;
@@ -91,6 +100,9 @@ pub mod miya_bridge {
         let bridge = &ctx.accounts.bridge;
         let token_pair = &mut ctx.accounts.token_pair;
         
+        // Reject Token-2022 extensions that would break lock/release accounting
+        validate_mint_extensions(&ctx.accounts.target_token_mint)?;
+        
         // Only the bridge authority can register token pairs
         require!(
             ctx.accounts.authority.key() == bridge.authority,
@@ -109,6 +121,17 @@ pub mod miya_bridge {
         token_pair.total_locked = 0;
         token_pair.total_released = 0;
         
+        let (_, bump) = Pubkey::find_program_address(
+            &[
+                b"miya_token_pair".as_ref(),
+                &source_chain_id.to_le_bytes(),
+                &target_chain_id.to_le_bytes(),
+                ctx.accounts.target_token_mint.key().as_ref(),
+            ],
+            ctx.program_id,
+        );
+        token_pair.bump = bump;
+        
         emit!(TokenPairRegisteredEvent {
             source_chain_id,
             target_chain_id,
@@ -121,6 +144,37 @@ pub mod miya_bridge {
         Ok(())
     }
 
+    pub fn update_token_pair_fee(
+        ctx: Context<UpdateTokenPair>,
+        fee_percentage: u16,
+    ) -> Result<()> {
+        let bridge = &ctx.accounts.bridge;
+        let token_pair = &mut ctx.accounts.token_pair;
+        
+        // Only the bridge authority can update token pair fees
+        require!(
+            ctx.accounts.authority.key() == bridge.authority,
+            BridgeError::Unauthorized
+        );
+        
+        // Validate fee percentage (max 10%)
+        require!(fee_percentage <= 1000, BridgeError::FeeTooHigh);
+        
+        let old_fee_percentage = token_pair.fee_percentage;
+        token_pair.fee_percentage = fee_percentage;
+        
+        emit!(TokenPairFeeUpdatedEvent {
+            token_pair: token_pair.key(),
+            old_fee_percentage,
+            fee_percentage,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Updated token pair fee: {} -> {}", old_fee_percentage, fee_percentage);
+        
+        Ok(())
+    }
+
     pub fn lock_tokens(
         ctx: Context<LockTokens>,
         amount: u64,
@@ -152,13 +206,14 @@ pub mod miya_bridge {
         // Transfer tokens from user to bridge vault
         let transfer_ctx = CpiContext::new(
             ctx.accounts.token_program.to_account_info(),
-            Transfer {
+            TransferChecked {
                 from: ctx.accounts.user_token_account.to_account_info(),
+                mint: ctx.accounts.target_token_mint.to_account_info(),
                 to: ctx.accounts.bridge_vault.to_account_info(),
                 authority: ctx.accounts.signer.to_account_info(),
             },
         );
-        token::transfer(transfer_ctx, amount)?;
+        token_interface::transfer_checked(transfer_ctx, amount, ctx.accounts.target_token_mint.decimals)?;
         
         // Update accounting
         token_pair.total_locked = token_pair.total_locked.checked_add(amount).ok_or(BridgeError::ArithmeticError)?;
@@ -231,30 +286,33 @@ pub mod miya_bridge {
             &[bridge.bump],
         ];
         let signer = &[&seeds[..]];
+        let decimals = ctx.accounts.target_token_mint.decimals;
         
         let transfer_ctx = CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
-            Transfer {
+            TransferChecked {
                 from: ctx.accounts.bridge_vault.to_account_info(),
+                mint: ctx.accounts.target_token_mint.to_account_info(),
                 to: ctx.accounts.recipient_token_account.to_account_info(),
                 authority: ctx.accounts.bridge.to_account_info(),
             },
             signer,
         );
-        token::transfer(transfer_ctx, release_amount)?;
+        token_interface::transfer_checked(transfer_ctx, release_amount, decimals)?;
         
         // If there's a fee, transfer it to the fee account
         if fee_amount > 0 {
             let fee_transfer_ctx = CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
-                Transfer {
+                TransferChecked {
                     from: ctx.accounts.bridge_vault.to_account_info(),
+                    mint: ctx.accounts.target_token_mint.to_account_info(),
                     to: ctx.accounts.fee_account.to_account_info(),
                     authority: ctx.accounts.bridge.to_account_info(),
                 },
                 signer,
             );
-            token::transfer(fee_transfer_ctx, fee_amount)?;
+            token_interface::transfer_checked(fee_transfer_ctx, fee_amount, decimals)?;
         }
         
         // Update accounting
@@ -312,6 +370,110 @@ pub mod miya_bridge {
         
         Ok(())
     }
+
+    pub fn transfer_bridge_authority(
+        ctx: Context<UpdateBridge>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        let bridge = &mut ctx.accounts.bridge;
+        
+        // Only the current bridge authority can transfer authority
+        require!(
+            ctx.accounts.authority.key() == bridge.authority,
+            BridgeError::Unauthorized
+        );
+        
+        // The transfer only completes once the new authority accepts it
+        bridge.pending_authority = Some(new_authority);
+        
+        emit!(AuthorityTransferProposedEvent {
+            bridge: bridge.key(),
+            authority: bridge.authority,
+            pending_authority: new_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Bridge authority transfer proposed to: {}", new_authority);
+        
+        Ok(())
+    }
+
+    pub fn accept_bridge_authority(ctx: Context<AcceptBridgeAuthority>) -> Result<()> {
+        let bridge = &mut ctx.accounts.bridge;
+        let new_authority = ctx.accounts.new_authority.key();
+        
+        // Only the pending authority can accept the transfer
+        let pending_authority = bridge.pending_authority.ok_or(BridgeError::NoPendingAuthority)?;
+        require!(
+            new_authority == pending_authority,
+            BridgeError::Unauthorized
+        );
+        
+        // Update the authority
+        let old_authority = bridge.authority;
+        bridge.authority = new_authority;
+        bridge.pending_authority = None;
+        
+        emit!(AuthorityTransferredEvent {
+            bridge: bridge.key(),
+            old_authority,
+            new_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Bridge authority transferred to: {}", new_authority);
+        
+        Ok(())
+    }
+
+    pub fn cancel_bridge_authority_transfer(ctx: Context<UpdateBridge>) -> Result<()> {
+        let bridge = &mut ctx.accounts.bridge;
+        
+        // Only the current bridge authority can cancel a pending transfer
+        require!(
+            ctx.accounts.authority.key() == bridge.authority,
+            BridgeError::Unauthorized
+        );
+        
+        let pending_authority = bridge.pending_authority.take().ok_or(BridgeError::NoPendingAuthority)?;
+        
+        emit!(AuthorityTransferCanceledEvent {
+            bridge: bridge.key(),
+            authority: bridge.authority,
+            pending_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Bridge authority transfer canceled");
+        
+        Ok(())
+    }
+}
+
+// Extensions outside this list could let the vault balance drift from total_locked /
+// total_released (fees, hooks, confidential transfers, permanent delegates, a close authority
+// re-creating the mint with other extensions), so those mints are refused
+fn validate_mint_extensions(mint: &InterfaceAccount<Mint>) -> Result<()> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != spl_token_2022::ID {
+        return Ok(());
+    }
+    
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+    for extension in mint_state.get_extension_types()? {
+        require!(
+            matches!(
+                extension,
+                ExtensionType::InterestBearingConfig
+                    | ExtensionType::MetadataPointer
+                    | ExtensionType::TokenMetadata
+            ),
+            BridgeError::UnsupportedMintExtension
+        );
+    }
+    
+    Ok(())
 }
 
 // Placeholder for proof verification
@@ -344,6 +506,7 @@ pub struct InitializeBridge<'info> {
 }
 
 #[derive(Accounts)]
+#[instruction(chain_id: u16)]
 pub struct ManageSupportedChain<'info> {
     #[account(seeds = [b"miya_bridge"], bump = bridge.bump)]
     pub bridge: Account<'info, Bridge>,
@@ -364,6 +527,22 @@ pub struct ManageSupportedChain<'info> {
 }
 
 #[derive(Accounts)]
+pub struct UpdateSupportedChain<'info> {
+    #[account(seeds = [b"miya_bridge"], bump = bridge.bump)]
+    pub bridge: Account<'info, Bridge>,
+    
+    #[account(
+        mut,
+        seeds = [b"miya_chain", &supported_chain.chain_id.to_le_bytes()],
+        bump
+    )]
+    pub supported_chain: Account<'info, SupportedChain>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(source_chain_id: u16, target_chain_id: u16)]
 pub struct RegisterTokenPair<'info> {
     #[account(seeds = [b"miya_bridge"], bump = bridge.bump)]
     pub bridge: Account<'info, Bridge>,
@@ -394,7 +573,7 @@ pub struct RegisterTokenPair<'info> {
     )]
     pub supported_target_chain: Account<'info, SupportedChain>,
     
-    pub target_token_mint: Account<'info, Mint>,
+    pub target_token_mint: InterfaceAccount<'info, Mint>,
     
     #[account(mut)]
     pub authority: Signer<'info>,
@@ -402,6 +581,26 @@ pub struct RegisterTokenPair<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct UpdateTokenPair<'info> {
+    #[account(seeds = [b"miya_bridge"], bump = bridge.bump)]
+    pub bridge: Account<'info, Bridge>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"miya_token_pair",
+            &token_pair.source_chain_id.to_le_bytes(),
+            &token_pair.target_chain_id.to_le_bytes(),
+            token_pair.target_token_mint.as_ref(),
+        ],
+        bump = token_pair.bump,
+    )]
+    pub token_pair: Account<'info, TokenPair>,
+    
+    pub authority: Signer<'info>,
+}
+
 #[derive(Accounts)]
 pub struct LockTokens<'info> {
     #[account(
@@ -431,15 +630,18 @@ pub struct LockTokens<'info> {
         constraint = user_token_account.owner == signer.key(),
         constraint = user_token_account.mint == token_pair.target_token_mint,
     )]
-    pub user_token_account: Account<'info, TokenAccount>,
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
     
     #[account(
         mut,
         constraint = bridge_vault.mint == token_pair.target_token_mint,
     )]
-    pub bridge_vault: Account<'info, TokenAccount>,
+    pub bridge_vault: InterfaceAccount<'info, TokenAccount>,
     
-    pub token_program: Program<'info, Token>,
+    #[account(address = token_pair.target_token_mint)]
+    pub target_token_mint: InterfaceAccount<'info, Mint>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
@@ -471,21 +673,24 @@ pub struct ReleaseTokens<'info> {
         constraint = recipient_token_account.owner == recipient.key(),
         constraint = recipient_token_account.mint == token_pair.target_token_mint,
     )]
-    pub recipient_token_account: Account<'info, TokenAccount>,
+    pub recipient_token_account: InterfaceAccount<'info, TokenAccount>,
     
     #[account(
         mut,
         constraint = bridge_vault.mint == token_pair.target_token_mint,
     )]
-    pub bridge_vault: Account<'info, TokenAccount>,
+    pub bridge_vault: InterfaceAccount<'info, TokenAccount>,
     
     #[account(
         mut,
         constraint = fee_account.mint == token_pair.target_token_mint,
     )]
-    pub fee_account: Account<'info, TokenAccount>,
+    pub fee_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = token_pair.target_token_mint)]
+    pub target_token_mint: InterfaceAccount<'info, Mint>,
     
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
@@ -500,9 +705,22 @@ pub struct UpdateBridge<'info> {
     pub authority: Signer<'info>,
 }
 
+#[derive(Accounts)]
+pub struct AcceptBridgeAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"miya_bridge"],
+        bump = bridge.bump
+    )]
+    pub bridge: Account<'info, Bridge>,
+    
+    pub new_authority: Signer<'info>,
+}
+
 #[account]
 pub struct Bridge {
     pub authority: Pubkey,
+    pub pending_authority: Option<Pubkey>, // Proposed authority awaiting acceptance
     pub is_active: bool,
     pub supported_chain_count: u16,
     pub total_locked_tokens: u64,
@@ -511,7 +729,7 @@ pub struct Bridge {
 }
 
 impl Bridge {
-    pub const LEN: usize = 32 + 1 + 2 + 8 + 8 + 1;
+    pub const LEN: usize = 32 + 33 + 1 + 2 + 8 + 8 + 1;
 }
 
 #[account]
@@ -570,6 +788,14 @@ pub struct TokenPairRegisteredEvent {
     pub timestamp: i64,
 }
 
+#[event]
+pub struct TokenPairFeeUpdatedEvent {
+    pub token_pair: Pubkey,
+    pub old_fee_percentage: u16,
+    pub fee_percentage: u16,
+    pub timestamp: i64,
+}
+
 #[event]
 pub struct TokensLockedEvent {
     pub user: Pubkey,
@@ -591,6 +817,30 @@ pub struct TokensReleasedEvent {
     pub timestamp: i64,
 }
 
+#[event]
+pub struct AuthorityTransferProposedEvent {
+    pub bridge: Pubkey,
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AuthorityTransferredEvent {
+    pub bridge: Pubkey,
+    pub old_authority: Pubkey,
+    pub new_authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AuthorityTransferCanceledEvent {
+    pub bridge: Pubkey,
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+    pub timestamp: i64,
+}
+
 #[error_code]
 pub enum BridgeError {
     #[msg("Unauthorized operation")]
@@ -628,4 +878,10 @@ pub enum BridgeError {
     
     #[msg("Arithmetic error")]
     ArithmeticError,
+    
+    #[msg("No authority transfer is pending")]
+    NoPendingAuthority,
+    
+    #[msg("Token mint has an unsupported Token-2022 extension")]
+    UnsupportedMintExtension,
 } 
\ No newline at end of file
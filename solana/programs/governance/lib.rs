@@ -1,7 +1,19 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::keccak;
+use anchor_lang::solana_program::program::{get_return_data, invoke, invoke_signed};
+use anchor_lang::system_program;
+use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+use anchor_lang::{InstructionData, ToAccountMetas};
 
 declare_id!("Governance111111111111111111111111111111111");
 
+// The other MIYA programs administered through proposals, as deployed in Anchor.toml
+pub const MIXER_PROGRAM_ID: Pubkey = anchor_lang::solana_program::pubkey!("Mixer111111111111111111111111111111111111111");
+pub const BRIDGE_PROGRAM_ID: Pubkey = anchor_lang::solana_program::pubkey!("Bridge1111111111111111111111111111111111111");
+pub const ZKENGINE_PROGRAM_ID: Pubkey = anchor_lang::solana_program::pubkey!("ZkEngine11111111111111111111111111111111111");
+
 #[program]
 pub mod miya_governance {
     use super::*;
@@ -9,13 +21,33 @@ pub mod miya_governance {
     pub fn initialize_dao(ctx: Context<InitializeDao>, name: String, min_voting_period: i64, max_voting_period: i64) -> Result<()> {
         let dao = &mut ctx.accounts.dao;
         dao.authority = ctx.accounts.authority.key();
+        dao.pending_authority = None;
         dao.name = name;
         dao.min_voting_period = min_voting_period;
         dao.max_voting_period = max_voting_period;
         dao.proposal_count = 0;
         dao.is_active = true;
+        dao.governance_mint = ctx.accounts.governance_mint.key();
+        dao.min_proposal_weight = 0;
+        dao.proposal_deposit = 0;
+        dao.quorum_votes = 0;
+        dao.execution_delay = 0;
+        dao.veto_council = Vec::new();
+        dao.veto_threshold = 0;
+        dao.veto_council_version = 0;
+        dao.voting_strategy = VotingStrategy::Linear;
+        dao.reveal_period = 0;
+        dao.anonymous_voting = false;
+        
+        // The treasury is a data-less, system-owned PDA so it can hold lamports
+        // and own token accounts, and only this program can sign for it
+        let (_, treasury_bump) = Pubkey::find_program_address(
+            &[b"miya_treasury".as_ref(), dao.key().as_ref()],
+            ctx.program_id,
+        );
+        dao.treasury_bump = treasury_bump;
         
-        msg!("DAO initialized: {}", name);
+        msg!("DAO initialized: {}", dao.name);
         
         Ok(())
     }
@@ -23,9 +55,11 @@ pub mod miya_governance {
     pub fn create_proposal(
         ctx: Context<CreateProposal>,
         title: String,
-        description: String,
-        actions: Vec<ProposalAction>,
+        content_uri: String,
+        content_hash: [u8; 32],
         voting_period: i64,
+        vote_type: VoteType,
+        options: Vec<String>,
     ) -> Result<()> {
         let dao = &mut ctx.accounts.dao;
         let proposal = &mut ctx.accounts.proposal;
@@ -36,8 +70,28 @@ pub mod miya_governance {
         
         // Validate proposal parameters
         require!(!title.is_empty(), GovernanceError::EmptyTitle);
-        require!(!description.is_empty(), GovernanceError::EmptyDescription);
-        require!(!actions.is_empty(), GovernanceError::NoActions);
+        require!(title.len() <= Proposal::MAX_TITLE_LEN, GovernanceError::TitleTooLong);
+        require!(!content_uri.is_empty(), GovernanceError::EmptyContentUri);
+        require!(
+            content_uri.len() <= Proposal::MAX_CONTENT_URI_LEN,
+            GovernanceError::ContentUriTooLong
+        );
+        
+        // Binary proposals have no options, multiple-choice proposals need at least two
+        if vote_type == VoteType::Binary {
+            require!(options.is_empty(), GovernanceError::InvalidOptions);
+        } else {
+            require!(
+                options.len() >= 2 && options.len() <= Proposal::MAX_OPTIONS,
+                GovernanceError::InvalidOptions
+            );
+        }
+        for label in options.iter() {
+            require!(
+                !label.is_empty() && label.len() <= ProposalOption::MAX_LABEL_LEN,
+                GovernanceError::InvalidOptions
+            );
+        }
         
         // Validate voting period
         require!(
@@ -45,24 +99,68 @@ pub mod miya_governance {
             GovernanceError::InvalidVotingPeriod
         );
         
-        // Calculate proposal end time
+        // Ensure the proposer has enough voting power; locked tokens cannot be
+        // moved to another wallet to meet the threshold again. Without an escrow
+        // the proposer can only meet a zero threshold.
         let current_time = Clock::get()?.unix_timestamp;
-        let end_time = current_time.checked_add(voting_period).ok_or(GovernanceError::ArithmeticError)?;
+        let voting_power = match &ctx.accounts.vote_escrow {
+            Some(vote_escrow) => vote_escrow.voting_power_at(current_time)?,
+            None => 0,
+        };
+        require!(
+            voting_power >= dao.min_proposal_weight,
+            GovernanceError::ProposalThresholdNotMet
+        );
         
-        // Setup the proposal
+        // Setup the proposal as a draft; the voting clock starts at sign-off
         proposal.dao = dao.key();
         proposal.proposer = proposer.key();
         proposal.title = title;
-        proposal.description = description;
-        proposal.actions = actions;
+        proposal.content_uri = content_uri;
+        proposal.content_hash = content_hash;
+        proposal.state = ProposalState::Draft;
+        proposal.vote_type = vote_type;
+        proposal.voting_strategy = dao.voting_strategy;
+        proposal.reveal_period = dao.reveal_period;
+        proposal.is_anonymous = dao.anonymous_voting;
+        proposal.voter_root = [0; 32];
+        proposal.member_quorum = 0;
+        proposal.options = options
+            .into_iter()
+            .map(|label| ProposalOption {
+                label,
+                vote_weight: 0,
+                action_count: 0,
+            })
+            .collect();
+        proposal.winning_option = None;
+        proposal.action_count = 0;
+        proposal.next_action_index = 0;
+        proposal.executed_action_count = 0;
         proposal.created_at = current_time;
-        proposal.ends_at = end_time;
-        proposal.is_executed = false;
-        proposal.is_canceled = false;
+        proposal.voting_period = voting_period;
+        proposal.voting_starts_at = 0;
+        proposal.ends_at = 0;
+        proposal.executable_at = 0;
+        proposal.veto_approvals = 0;
+        proposal.veto_council_version = dao.veto_council_version;
         proposal.for_votes = 0;
         proposal.against_votes = 0;
         proposal.abstain_votes = 0;
         proposal.id = dao.proposal_count;
+        proposal.deposit_amount = dao.proposal_deposit;
+        
+        // Escrow the anti-spam deposit in the proposal account itself
+        if dao.proposal_deposit > 0 {
+            let transfer_ctx = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: proposer.to_account_info(),
+                    to: proposal.to_account_info(),
+                },
+            );
+            system_program::transfer(transfer_ctx, dao.proposal_deposit)?;
+        }
         
         // Increment proposal count
         dao.proposal_count = dao.proposal_count.checked_add(1).ok_or(GovernanceError::ArithmeticError)?;
@@ -72,23 +170,181 @@ pub mod miya_governance {
             proposal: proposal.key(),
             proposer: proposer.key(),
             id: proposal.id,
+            content_hash,
             timestamp: current_time,
         });
         
-        msg!("Proposal created: {}", title);
+        msg!("Proposal created: {}", proposal.title);
         
         Ok(())
     }
 
-    pub fn cast_vote(
-        ctx: Context<CastVote>,
-        vote: Vote,
-        vote_weight: u64,
+    pub fn add_proposal_action(
+        ctx: Context<AddProposalAction>,
+        option_index: u8,
+        action: ProposalAction,
+    ) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+        let action_record = &mut ctx.accounts.action_record;
+        
+        // Actions can only be added to a draft by its proposer
+        require!(
+            proposal.state == ProposalState::Draft,
+            GovernanceError::ProposalNotDraft
+        );
+        
+        add_option_action(proposal, option_index)?;
+        
+        action_record.proposal = proposal.key();
+        action_record.index = proposal.next_action_index;
+        action_record.option_index = option_index;
+        action_record.action = action;
+        action_record.is_executed = false;
+        
+        proposal.next_action_index = proposal.next_action_index.checked_add(1).ok_or(GovernanceError::ArithmeticError)?;
+        proposal.action_count = proposal.action_count.checked_add(1).ok_or(GovernanceError::ArithmeticError)?;
+        
+        msg!("Proposal action added: {}", action_record.index);
+        
+        Ok(())
+    }
+
+    pub fn add_protocol_action(
+        ctx: Context<AddProtocolAction>,
+        option_index: u8,
+        protocol_action: ProtocolAction,
+    ) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+        let action_record = &mut ctx.accounts.action_record;
+        
+        // Actions can only be added to a draft by its proposer
+        require!(
+            proposal.state == ProposalState::Draft,
+            GovernanceError::ProposalNotDraft
+        );
+        
+        protocol_action.validate()?;
+        add_option_action(proposal, option_index)?;
+        
+        // Typed protocol actions always act with the DAO treasury as the admin authority
+        action_record.proposal = proposal.key();
+        action_record.index = proposal.next_action_index;
+        action_record.option_index = option_index;
+        action_record.action = protocol_action.to_proposal_action(ctx.accounts.dao.key(), ctx.accounts.treasury.key());
+        action_record.is_executed = false;
+        
+        proposal.next_action_index = proposal.next_action_index.checked_add(1).ok_or(GovernanceError::ArithmeticError)?;
+        proposal.action_count = proposal.action_count.checked_add(1).ok_or(GovernanceError::ArithmeticError)?;
+        
+        msg!("Protocol action added: {}", action_record.index);
+        
+        Ok(())
+    }
+
+    pub fn remove_proposal_action(ctx: Context<RemoveProposalAction>) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+        
+        // Actions can only be removed from a draft by its proposer
+        require!(
+            proposal.state == ProposalState::Draft,
+            GovernanceError::ProposalNotDraft
+        );
+        
+        proposal.action_count = proposal.action_count.checked_sub(1).ok_or(GovernanceError::ArithmeticError)?;
+        if let Some(option) = proposal.options.get_mut(ctx.accounts.action_record.option_index as usize) {
+            option.action_count = option.action_count.checked_sub(1).ok_or(GovernanceError::ArithmeticError)?;
+        }
+        
+        msg!("Proposal action removed: {}", ctx.accounts.action_record.index);
+        
+        Ok(())
+    }
+
+    pub fn close_action_record(ctx: Context<CloseActionRecord>) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+        
+        // Records are kept until the proposal can no longer execute them
+        require!(
+            matches!(
+                proposal.state,
+                ProposalState::Executed
+                    | ProposalState::Canceled
+                    | ProposalState::Defeated
+                    | ProposalState::Vetoed
+                    | ProposalState::Expired
+            ),
+            GovernanceError::ProposalNotClosable
+        );
+        
+        proposal.action_count = proposal.action_count.checked_sub(1).ok_or(GovernanceError::ArithmeticError)?;
+        if let Some(option) = proposal.options.get_mut(ctx.accounts.action_record.option_index as usize) {
+            option.action_count = option.action_count.checked_sub(1).ok_or(GovernanceError::ArithmeticError)?;
+        }
+        
+        msg!("Proposal action record closed: {}", ctx.accounts.action_record.index);
+        
+        Ok(())
+    }
+
+    pub fn sign_off_proposal<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SignOffProposal<'info>>,
     ) -> Result<()> {
+        let dao = &ctx.accounts.dao;
+        let proposal = &mut ctx.accounts.proposal;
+        
+        // Ensure the DAO is active
+        require!(dao.is_active, GovernanceError::DaoInactive);
+        
+        require!(
+            proposal.state == ProposalState::Draft,
+            GovernanceError::ProposalNotDraft
+        );
+        // Binary proposals need something to execute; options of a multiple-choice
+        // proposal may be left without actions
+        require!(
+            proposal.vote_type != VoteType::Binary || proposal.action_count > 0,
+            GovernanceError::NoActions
+        );
+        
+        // Anonymous proposals snapshot the voter registry, passed as the first remaining account
+        if proposal.is_anonymous {
+            let registry_info = ctx.remaining_accounts.first().ok_or(GovernanceError::MissingVoterRegistry)?;
+            let (registry_key, _) = Pubkey::find_program_address(
+                &[b"miya_voter_registry".as_ref(), dao.key().as_ref()],
+                ctx.program_id,
+            );
+            require!(registry_info.key() == registry_key, GovernanceError::MissingVoterRegistry);
+            let registry = Account::<VoterRegistry>::try_from(registry_info)?;
+            proposal.voter_root = registry.root;
+            proposal.member_quorum = registry.quorum_members;
+        }
+        
+        // Start the voting clock
+        let current_time = Clock::get()?.unix_timestamp;
+        proposal.voting_starts_at = current_time;
+        proposal.ends_at = current_time.checked_add(proposal.voting_period).ok_or(GovernanceError::ArithmeticError)?;
+        proposal.state = ProposalState::Voting;
+        
+        emit!(ProposalSignedOffEvent {
+            dao: dao.key(),
+            proposal: proposal.key(),
+            voting_starts_at: proposal.voting_starts_at,
+            ends_at: proposal.ends_at,
+        });
+        
+        msg!("Proposal signed off: {}", proposal.title);
+        
+        Ok(())
+    }
+
+    pub fn cast_vote(ctx: Context<CastVote>, vote: Vote) -> Result<()> {
         let proposal = &mut ctx.accounts.proposal;
         let voter = &ctx.accounts.voter;
         let vote_record = &mut ctx.accounts.vote_record;
         
+        // Voting is frozen while the DAO is deactivated
+        require!(ctx.accounts.dao.is_active, GovernanceError::DaoInactive);
+        
         // Ensure the proposal is active
         let current_time = Clock::get()?.unix_timestamp;
         require!(
@@ -96,9 +352,11 @@ pub mod miya_governance {
             GovernanceError::VotingEnded
         );
         require!(
-            !proposal.is_executed && !proposal.is_canceled,
+            proposal.state == ProposalState::Voting,
             GovernanceError::ProposalNotActive
         );
+        require!(proposal.reveal_period == 0, GovernanceError::PrivateVotingRequired);
+        require!(!proposal.is_anonymous, GovernanceError::AnonymousVotingRequired);
         
         // Prevent double voting
         require!(
@@ -106,32 +364,29 @@ pub mod miya_governance {
             GovernanceError::AlreadyVoted
         );
         
+        // Weight is the voter's locked balance, decayed to the proposal snapshot
+        let voting_power = ctx.accounts.vote_escrow.voting_power_at(proposal.voting_starts_at)?;
+        let vote_weight = proposal.voting_strategy.effective_weight(voting_power)?;
+        require!(vote_weight > 0, GovernanceError::NoVotingPower);
+        
         // Record the vote
         vote_record.proposal = proposal.key();
         vote_record.voter = voter.key();
-        vote_record.vote = vote;
+        vote_record.vote = vote.clone();
         vote_record.weight = vote_weight;
         vote_record.has_voted = true;
+        vote_record.commitment = None;
         vote_record.timestamp = current_time;
+        vote_record.reward_claimed = false;
         
         // Update vote totals
-        match vote {
-            Vote::For => {
-                proposal.for_votes = proposal.for_votes.checked_add(vote_weight).ok_or(GovernanceError::ArithmeticError)?;
-            },
-            Vote::Against => {
-                proposal.against_votes = proposal.against_votes.checked_add(vote_weight).ok_or(GovernanceError::ArithmeticError)?;
-            },
-            Vote::Abstain => {
-                proposal.abstain_votes = proposal.abstain_votes.checked_add(vote_weight).ok_or(GovernanceError::ArithmeticError)?;
-            },
-        }
+        add_vote(proposal, &vote, vote_weight)?;
         
         emit!(VoteCastEvent {
             dao: proposal.dao,
             proposal: proposal.key(),
             voter: voter.key(),
-            vote,
+            vote: vote.clone(),
             weight: vote_weight,
             timestamp: current_time,
         });
@@ -141,363 +396,3199 @@ pub mod miya_governance {
         Ok(())
     }
 
-    pub fn execute_proposal(ctx: Context<ExecuteProposal>) -> Result<()> {
+    pub fn change_vote(ctx: Context<ChangeVote>, vote: Vote) -> Result<()> {
         let proposal = &mut ctx.accounts.proposal;
+        let vote_record = &mut ctx.accounts.vote_record;
         
-        // Ensure voting has ended
+        require!(ctx.accounts.dao.is_active, GovernanceError::DaoInactive);
+        
+        // Votes can only change while voting is open
         let current_time = Clock::get()?.unix_timestamp;
         require!(
-            current_time >= proposal.ends_at,
-            GovernanceError::VotingNotEnded
+            current_time < proposal.ends_at,
+            GovernanceError::VotingEnded
         );
-        
-        // Ensure the proposal hasn't been executed or canceled
         require!(
-            !proposal.is_executed && !proposal.is_canceled,
+            proposal.state == ProposalState::Voting,
             GovernanceError::ProposalNotActive
         );
+        require!(proposal.reveal_period == 0, GovernanceError::PrivateVotingRequired);
         
-        // Check if proposal passed (simple majority)
-        require!(
-            proposal.for_votes > proposal.against_votes,
-            GovernanceError::ProposalRejected
-        );
+        let voting_power = ctx.accounts.vote_escrow.voting_power_at(proposal.voting_starts_at)?;
+        let vote_weight = proposal.voting_strategy.effective_weight(voting_power)?;
+        require!(vote_weight > 0, GovernanceError::NoVotingPower);
+        
+        // Remove the previous vote from the tally, if it is still counted
+        let previous_vote = if vote_record.has_voted {
+            remove_vote(proposal, &vote_record.vote, vote_record.weight)?;
+            Some(vote_record.vote.clone())
+        } else {
+            None
+        };
+        let previous_weight = vote_record.weight;
         
-        // Mark proposal as executed
-        proposal.is_executed = true;
+        // Apply the new vote
+        add_vote(proposal, &vote, vote_weight)?;
         
-        // Execute the proposal actions (in a real implementation)
-        // This would involve cross-program invocation to execute various actions
-        // For this prototype, we just emit an event
+        vote_record.vote = vote.clone();
+        vote_record.weight = vote_weight;
+        vote_record.has_voted = true;
+        vote_record.timestamp = current_time;
         
-        emit!(ProposalExecutedEvent {
+        emit!(VoteChangedEvent {
             dao: proposal.dao,
             proposal: proposal.key(),
-            executor: ctx.accounts.executor.key(),
+            voter: ctx.accounts.voter.key(),
+            previous_vote,
+            previous_weight,
+            vote: Some(vote.clone()),
+            weight: vote_weight,
             timestamp: current_time,
         });
         
-        msg!("Proposal executed: {}", proposal.title);
+        msg!("Vote changed: {:?}", vote);
         
         Ok(())
     }
 
-    pub fn cancel_proposal(ctx: Context<CancelProposal>) -> Result<()> {
-        let dao = &ctx.accounts.dao;
+    pub fn relinquish_vote(ctx: Context<RelinquishVote>) -> Result<()> {
         let proposal = &mut ctx.accounts.proposal;
+        let vote_record = &mut ctx.accounts.vote_record;
         
-        // Ensure the proposal hasn't been executed
+        require!(ctx.accounts.dao.is_active, GovernanceError::DaoInactive);
+        
+        // Votes can only be withdrawn while voting is open
+        let current_time = Clock::get()?.unix_timestamp;
         require!(
-            !proposal.is_executed && !proposal.is_canceled,
+            current_time < proposal.ends_at,
+            GovernanceError::VotingEnded
+        );
+        require!(
+            proposal.state == ProposalState::Voting,
             GovernanceError::ProposalNotActive
         );
+        require!(proposal.reveal_period == 0, GovernanceError::PrivateVotingRequired);
+        require!(vote_record.has_voted, GovernanceError::NotVoted);
         
-        // Only the proposer or DAO authority can cancel
-        let is_proposer = ctx.accounts.canceler.key() == proposal.proposer;
-        let is_authority = ctx.accounts.canceler.key() == dao.authority;
+        // Remove the vote from the tally
+        remove_vote(proposal, &vote_record.vote, vote_record.weight)?;
+        
+        let previous_weight = vote_record.weight;
+        vote_record.weight = 0;
+        vote_record.has_voted = false;
+        vote_record.timestamp = current_time;
+        
+        emit!(VoteChangedEvent {
+            dao: proposal.dao,
+            proposal: proposal.key(),
+            voter: ctx.accounts.voter.key(),
+            previous_vote: Some(vote_record.vote.clone()),
+            previous_weight,
+            vote: None,
+            weight: 0,
+            timestamp: current_time,
+        });
+        
+        msg!("Vote relinquished");
+        
+        Ok(())
+    }
+
+    pub fn commit_vote(ctx: Context<CommitVote>, commitment: [u8; 32]) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        let vote_record = &mut ctx.accounts.vote_record;
+        
+        require!(ctx.accounts.dao.is_active, GovernanceError::DaoInactive);
+        
+        // Commitments are accepted, and may be replaced, while voting is open
+        let current_time = Clock::get()?.unix_timestamp;
         require!(
-            is_proposer || is_authority,
-            GovernanceError::Unauthorized
+            current_time < proposal.ends_at,
+            GovernanceError::VotingEnded
+        );
+        require!(
+            proposal.state == ProposalState::Voting,
+            GovernanceError::ProposalNotActive
         );
+        require!(proposal.reveal_period > 0, GovernanceError::PublicVotingRequired);
+        require!(!proposal.is_anonymous, GovernanceError::AnonymousVotingRequired);
         
-        // Mark proposal as canceled
-        proposal.is_canceled = true;
+        // Nothing is tallied until the vote is revealed
+        vote_record.proposal = proposal.key();
+        vote_record.voter = ctx.accounts.voter.key();
+        vote_record.vote = Vote::Abstain;
+        vote_record.weight = 0;
+        vote_record.has_voted = false;
+        vote_record.commitment = Some(commitment);
+        vote_record.timestamp = current_time;
+        vote_record.reward_claimed = false;
         
-        emit!(ProposalCanceledEvent {
-            dao: dao.key(),
+        emit!(VoteCommittedEvent {
+            dao: proposal.dao,
             proposal: proposal.key(),
-            canceler: ctx.accounts.canceler.key(),
-            timestamp: Clock::get()?.unix_timestamp,
+            voter: ctx.accounts.voter.key(),
+            timestamp: current_time,
         });
         
-        msg!("Proposal canceled: {}", proposal.title);
+        msg!("Vote committed");
         
         Ok(())
     }
 
-    pub fn update_dao_settings(
-        ctx: Context<UpdateDaoSettings>,
-        name: Option<String>,
-        min_voting_period: Option<i64>,
-        max_voting_period: Option<i64>,
+    pub fn reveal_vote(
+        ctx: Context<RevealVote>,
+        vote: Vote,
+        vote_weight: u64,
+        salt: [u8; 32],
     ) -> Result<()> {
-        let dao = &mut ctx.accounts.dao;
+        let proposal = &mut ctx.accounts.proposal;
+        let vote_record = &mut ctx.accounts.vote_record;
         
-        // Only the DAO authority can update settings
+        require!(ctx.accounts.dao.is_active, GovernanceError::DaoInactive);
+        
+        // Reveals are only accepted between the end of voting and the end of the reveal period
+        let current_time = Clock::get()?.unix_timestamp;
         require!(
-            ctx.accounts.authority.key() == dao.authority,
-            GovernanceError::Unauthorized
+            proposal.state == ProposalState::Voting,
+            GovernanceError::ProposalNotActive
         );
+        require!(
+            current_time >= proposal.ends_at,
+            GovernanceError::VotingNotEnded
+        );
+        require!(
+            current_time < proposal.reveal_ends_at()?,
+            GovernanceError::RevealPeriodEnded
+        );
+        require!(!vote_record.has_voted, GovernanceError::AlreadyVoted);
         
-        // Update provided settings
-        if let Some(new_name) = name {
-            dao.name = new_name;
-        }
+        let commitment = vote_record.commitment.ok_or(GovernanceError::NotVoted)?;
+        require!(
+            vote_commitment(&proposal.key(), &ctx.accounts.voter.key(), &vote, vote_weight, &salt)? == commitment,
+            GovernanceError::InvalidReveal
+        );
         
-        if let Some(new_min) = min_voting_period {
-            dao.min_voting_period = new_min;
-        }
+        // The committed weight cannot exceed the voter's weight at the snapshot
+        let voting_power = ctx.accounts.vote_escrow.voting_power_at(proposal.voting_starts_at)?;
+        let available_weight = proposal.voting_strategy.effective_weight(voting_power)?;
+        require!(vote_weight > 0, GovernanceError::NoVotingPower);
+        require!(vote_weight <= available_weight, GovernanceError::VoteWeightTooHigh);
         
-        if let Some(new_max) = max_voting_period {
-            dao.max_voting_period = new_max;
-        }
+        add_vote(proposal, &vote, vote_weight)?;
         
-        // Validate voting periods
-        require!(
-            dao.min_voting_period <= dao.max_voting_period,
-            GovernanceError::InvalidVotingPeriod
-        );
+        vote_record.vote = vote.clone();
+        vote_record.weight = vote_weight;
+        vote_record.has_voted = true;
+        vote_record.timestamp = current_time;
         
-        emit!(DaoUpdatedEvent {
-            dao: dao.key(),
-            authority: dao.authority,
-            timestamp: Clock::get()?.unix_timestamp,
+        emit!(VoteCastEvent {
+            dao: proposal.dao,
+            proposal: proposal.key(),
+            voter: ctx.accounts.voter.key(),
+            vote: vote.clone(),
+            weight: vote_weight,
+            timestamp: current_time,
         });
         
-        msg!("DAO settings updated: {}", dao.name);
+        msg!("Vote revealed: {:?}", vote);
         
         Ok(())
     }
 
-    pub fn transfer_dao_authority(
-        ctx: Context<TransferDaoAuthority>,
-        new_authority: Pubkey,
+    pub fn cast_anonymous_vote(
+        ctx: Context<CastAnonymousVote>,
+        vote: Vote,
+        nullifier: [u8; 32],
+        proof: Vec<u8>,
     ) -> Result<()> {
-        let dao = &mut ctx.accounts.dao;
+        let proposal = &mut ctx.accounts.proposal;
         
-        // Only the current DAO authority can transfer authority
+        require!(ctx.accounts.dao.is_active, GovernanceError::DaoInactive);
+        
+        let current_time = Clock::get()?.unix_timestamp;
         require!(
-            ctx.accounts.authority.key() == dao.authority,
-            GovernanceError::Unauthorized
+            current_time < proposal.ends_at,
+            GovernanceError::VotingEnded
+        );
+        require!(
+            proposal.state == ProposalState::Voting,
+            GovernanceError::ProposalNotActive
         );
+        require!(proposal.is_anonymous, GovernanceError::PublicVotingRequired);
+        require!(proposal.reveal_period == 0, GovernanceError::PrivateVotingRequired);
         
-        // Update the authority
-        let old_authority = dao.authority;
-        dao.authority = new_authority;
+        // The proof shows the nullifier belongs to a member of the snapshotted registry
+        // and binds it to this proposal and vote
+        let vote_hash = keccak::hash(&vote.try_to_vec()?).0;
+        let mut public_inputs = Vec::with_capacity(32 * 4);
+        public_inputs.extend_from_slice(&proposal.voter_root);
+        public_inputs.extend_from_slice(&nullifier);
+        public_inputs.extend_from_slice(proposal.key().as_ref());
+        public_inputs.extend_from_slice(&vote_hash);
         
-        emit!(AuthorityTransferredEvent {
-            dao: dao.key(),
-            old_authority,
-            new_authority,
-            timestamp: Clock::get()?.unix_timestamp,
+        let verified = verify_with_zkengine(
+            &ctx.accounts.verifier.to_account_info(),
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.zkengine_program.to_account_info(),
+            proof,
+            public_inputs,
+        )?;
+        require!(verified, GovernanceError::InvalidVoteProof);
+        
+        // The nullifier PDA can only be created once, so each member votes once per proposal
+        let vote_nullifier = &mut ctx.accounts.vote_nullifier;
+        vote_nullifier.proposal = proposal.key();
+        vote_nullifier.nullifier = nullifier;
+        
+        // Every registered member carries the same weight, measured against the registry quorum
+        add_vote(proposal, &vote, VoterRegistry::MEMBER_WEIGHT)?;
+        
+        emit!(AnonymousVoteCastEvent {
+            dao: proposal.dao,
+            proposal: proposal.key(),
+            nullifier,
+            vote: vote.clone(),
+            timestamp: current_time,
         });
         
-        msg!("DAO authority transferred to: {}", new_authority);
+        msg!("Anonymous vote cast: {:?}", vote);
         
         Ok(())
     }
-}
-
-#[derive(Accounts)]
-pub struct InitializeDao<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + Dao::LEN,
-        seeds = [b"miya_dao", name.as_bytes()],
-        bump
-    )]
-    pub dao: Account<'info, Dao>,
-    
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
 
-#[derive(Accounts)]
-pub struct CreateProposal<'info> {
-    #[account(
+    pub fn execute_proposal_action<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteProposalAction<'info>>,
+    ) -> Result<()> {
+        let dao = &ctx.accounts.dao;
+        let proposal = &mut ctx.accounts.proposal;
+        let action_record = &mut ctx.accounts.action_record;
+        
+        // Execution is blocked while the DAO is deactivated
+        require!(dao.is_active, GovernanceError::DaoInactive);
+        
+        // Ensure the proposal is queued and its timelock has elapsed
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(
+            proposal.state == ProposalState::Queued || proposal.state == ProposalState::Executing,
+            GovernanceError::ProposalNotQueued
+        );
+        require!(
+            current_time >= proposal.executable_at,
+            GovernanceError::TimelockNotElapsed
+        );
+        require!(!proposal.is_expired(current_time), GovernanceError::ProposalExpired);
+        
+        // Ensure this action hasn't been executed yet and belongs to the winning option
+        require!(!action_record.is_executed, GovernanceError::ActionAlreadyExecuted);
+        require!(
+            proposal.winning_option == Some(action_record.option_index),
+            GovernanceError::ActionNotInWinningOption
+        );
+        
+        // Mark the action as executed and persist it before any CPI so it cannot re-enter
+        action_record.is_executed = true;
+        proposal.executed_action_count = proposal.executed_action_count.checked_add(1).ok_or(GovernanceError::ArithmeticError)?;
+        proposal.state = if proposal.executed_action_count == winning_action_count(proposal) {
+            ProposalState::Executed
+        } else {
+            ProposalState::Executing
+        };
+        action_record.exit(ctx.program_id)?;
+        proposal.exit(ctx.program_id)?;
+        
+        // Snapshot treasury balances so outflows can be reported after execution
+        let treasury = ctx.accounts.treasury.to_account_info();
+        let balances_before = treasury_balances(&treasury, ctx.remaining_accounts)?;
+        
+        // Execute the action, signing with the treasury seeds
+        let dao_key = dao.key();
+        let seeds = &[
+            b"miya_treasury".as_ref(),
+            dao_key.as_ref(),
+            &[dao.treasury_bump],
+        ];
+        let signer = &[&seeds[..]];
+        
+        execute_action(&action_record.action, &treasury, ctx.remaining_accounts, signer)?;
+        
+        let balances_after = treasury_balances(&treasury, ctx.remaining_accounts)?;
+        for before in balances_before.iter() {
+            // A closed token account no longer shows up and counts as fully drained
+            let balance = balances_after
+                .iter()
+                .find(|after| after.account == before.account)
+                .map_or(0, |after| after.balance);
+            if balance < before.balance {
+                emit!(TreasuryWithdrawalEvent {
+                    dao: dao_key,
+                    proposal: proposal.key(),
+                    mint: before.mint,
+                    amount: before.balance - balance,
+                    balance,
+                    timestamp: current_time,
+                });
+            }
+        }
+        
+        emit!(ProposalActionExecutedEvent {
+            dao: dao_key,
+            proposal: proposal.key(),
+            index: action_record.index,
+            executor: ctx.accounts.executor.key(),
+            timestamp: current_time,
+        });
+        
+        if proposal.state == ProposalState::Executed {
+            emit!(ProposalExecutedEvent {
+                dao: proposal.dao,
+                proposal: proposal.key(),
+                executor: ctx.accounts.executor.key(),
+                timestamp: current_time,
+            });
+            
+            msg!("Proposal executed: {}", proposal.title);
+        }
+        
+        Ok(())
+    }
+
+    pub fn finalize_proposal(ctx: Context<FinalizeProposal>) -> Result<()> {
+        let dao = &ctx.accounts.dao;
+        let proposal = &mut ctx.accounts.proposal;
+        
+        require!(dao.is_active, GovernanceError::DaoInactive);
+        
+        // Ensure voting has ended
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(
+            proposal.state == ProposalState::Voting,
+            GovernanceError::ProposalNotActive
+        );
+        require!(
+            current_time >= proposal.ends_at,
+            GovernanceError::VotingNotEnded
+        );
+        
+        // Commit-reveal proposals also wait out the reveal period; unrevealed votes count for nothing
+        require!(
+            current_time >= proposal.reveal_ends_at()?,
+            GovernanceError::RevealPeriodNotEnded
+        );
+        
+        // Check if proposal passed (quorum and a winner beating the against votes)
+        proposal.winning_option = if quorum_reached(dao, proposal)? {
+            find_winning_option(proposal)
+        } else {
+            None
+        };
+        proposal.state = if proposal.winning_option.is_some() {
+            ProposalState::Succeeded
+        } else {
+            ProposalState::Defeated
+        };
+        
+        emit!(ProposalFinalizedEvent {
+            dao: dao.key(),
+            proposal: proposal.key(),
+            state: proposal.state,
+            winning_option: proposal.winning_option,
+            timestamp: current_time,
+        });
+        
+        msg!("Proposal finalized: {:?}", proposal.state);
+        
+        Ok(())
+    }
+
+    pub fn queue_proposal(ctx: Context<QueueProposal>) -> Result<()> {
+        let dao = &ctx.accounts.dao;
+        let proposal = &mut ctx.accounts.proposal;
+        
+        require!(dao.is_active, GovernanceError::DaoInactive);
+        
+        // Start the timelock, during which the veto council can still stop the proposal
+        let current_time = Clock::get()?.unix_timestamp;
+        proposal.queue(current_time, dao.execution_delay)?;
+        
+        // A winning option without actions has nothing left to do
+        if winning_action_count(proposal) == 0 {
+            proposal.state = ProposalState::Executed;
+        }
+        
+        emit!(ProposalQueuedEvent {
+            dao: dao.key(),
+            proposal: proposal.key(),
+            executable_at: proposal.executable_at,
+            timestamp: current_time,
+        });
+        
+        msg!("Proposal queued, executable at: {}", proposal.executable_at);
+        
+        Ok(())
+    }
+
+    // Anyone can expire a passed proposal nobody executed in time, so it can be closed
+    pub fn expire_proposal(ctx: Context<ExpireProposal>) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+        
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(proposal.is_expired(current_time), GovernanceError::ProposalNotExpired);
+        
+        proposal.state = ProposalState::Expired;
+        
+        emit!(ProposalExpiredEvent {
+            dao: proposal.dao,
+            proposal: proposal.key(),
+            timestamp: current_time,
+        });
+        
+        msg!("Proposal expired: {}", proposal.title);
+        
+        Ok(())
+    }
+
+    pub fn veto_proposal(ctx: Context<VetoProposal>) -> Result<()> {
+        let dao = &ctx.accounts.dao;
+        let proposal = &mut ctx.accounts.proposal;
+        let member = ctx.accounts.council_member.key();
+        
+        // Only members of the veto council can veto
+        let member_index = dao
+            .veto_council
+            .iter()
+            .position(|key| key == &member)
+            .ok_or(GovernanceError::NotVetoCouncilMember)?;
+        
+        // Vetoes are only possible after the vote passed and before the timelock ends
+        let current_time = Clock::get()?.unix_timestamp;
+        let in_veto_window = match proposal.state {
+            ProposalState::Succeeded => true,
+            ProposalState::Queued => current_time < proposal.executable_at,
+            _ => false,
+        };
+        require!(in_veto_window, GovernanceError::VetoWindowClosed);
+        
+        // Approvals collected under a previous council no longer count
+        if proposal.veto_council_version != dao.veto_council_version {
+            proposal.veto_approvals = 0;
+            proposal.veto_council_version = dao.veto_council_version;
+        }
+        
+        let member_bit = 1u8 << member_index;
+        require!(
+            proposal.veto_approvals & member_bit == 0,
+            GovernanceError::AlreadyApprovedVeto
+        );
+        proposal.veto_approvals |= member_bit;
+        
+        let approvals = proposal.veto_approvals.count_ones() as u8;
+        msg!("Veto approvals: {}/{}", approvals, dao.veto_threshold);
+        
+        if approvals >= dao.veto_threshold {
+            proposal.state = ProposalState::Vetoed;
+            
+            emit!(ProposalVetoedEvent {
+                dao: dao.key(),
+                proposal: proposal.key(),
+                approvals,
+                timestamp: current_time,
+            });
+            
+            msg!("Proposal vetoed: {}", proposal.title);
+        }
+        
+        Ok(())
+    }
+
+    pub fn deposit_to_treasury(ctx: Context<DepositToTreasury>, amount: u64) -> Result<()> {
+        require!(amount > 0, GovernanceError::InvalidAmount);
+        
+        // Transfer lamports from the depositor to the treasury PDA
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.depositor.to_account_info(),
+                to: ctx.accounts.treasury.to_account_info(),
+            },
+        );
+        system_program::transfer(transfer_ctx, amount)?;
+        
+        emit!(TreasuryDepositEvent {
+            dao: ctx.accounts.dao.key(),
+            depositor: ctx.accounts.depositor.key(),
+            mint: None,
+            amount,
+            balance: ctx.accounts.treasury.lamports(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Deposited {} lamports to treasury", amount);
+        
+        Ok(())
+    }
+
+    pub fn deposit_tokens_to_treasury(ctx: Context<DepositTokensToTreasury>, amount: u64) -> Result<()> {
+        require!(amount > 0, GovernanceError::InvalidAmount);
+        
+        // Transfer tokens from the depositor to a token account owned by the treasury
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.depositor_token_account.to_account_info(),
+                to: ctx.accounts.treasury_token_account.to_account_info(),
+                authority: ctx.accounts.depositor.to_account_info(),
+            },
+        );
+        token::transfer(transfer_ctx, amount)?;
+        
+        ctx.accounts.treasury_token_account.reload()?;
+        
+        emit!(TreasuryDepositEvent {
+            dao: ctx.accounts.dao.key(),
+            depositor: ctx.accounts.depositor.key(),
+            mint: Some(ctx.accounts.treasury_token_account.mint),
+            amount,
+            balance: ctx.accounts.treasury_token_account.amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Deposited {} tokens to treasury", amount);
+        
+        Ok(())
+    }
+
+    // Called by the treasury through an executed proposal; pools can be topped up until claims start
+    pub fn fund_vote_rewards(ctx: Context<FundVoteRewards>, amount: u64) -> Result<()> {
+        require!(amount > 0, GovernanceError::InvalidAmount);
+        require!(
+            ctx.accounts.proposal.state != ProposalState::Canceled,
+            GovernanceError::ProposalNotActive
+        );
+        // Shares are computed against the total, so a later top-up would be split unevenly
+        require!(
+            ctx.accounts.reward_pool.claimed_amount == 0 && !ctx.accounts.reward_pool.swept,
+            GovernanceError::RewardClaimsStarted
+        );
+        
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.treasury.to_account_info(),
+                to: ctx.accounts.reward_pool.to_account_info(),
+            },
+        );
+        system_program::transfer(transfer_ctx, amount)?;
+        
+        let reward_pool = &mut ctx.accounts.reward_pool;
+        reward_pool.proposal = ctx.accounts.proposal.key();
+        reward_pool.total_amount = reward_pool.total_amount
+            .checked_add(amount)
+            .ok_or(GovernanceError::ArithmeticError)?;
+        
+        emit!(VoteRewardsFundedEvent {
+            dao: ctx.accounts.dao.key(),
+            proposal: reward_pool.proposal,
+            amount,
+            total_amount: reward_pool.total_amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Funded {} lamports of vote rewards", amount);
+        
+        Ok(())
+    }
+
+    pub fn claim_vote_reward(ctx: Context<ClaimVoteReward>) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        let vote_record = &mut ctx.accounts.vote_record;
+        
+        // Rewards are paid once the tally is final
+        require!(
+            !matches!(
+                proposal.state,
+                ProposalState::Draft | ProposalState::Voting | ProposalState::Canceled
+            ),
+            GovernanceError::ProposalNotFinalized
+        );
+        require!(vote_record.has_voted, GovernanceError::NotVoted);
+        require!(!vote_record.reward_claimed, GovernanceError::RewardAlreadyClaimed);
+        require!(!ctx.accounts.reward_pool.swept, GovernanceError::RewardsSwept);
+        
+        // Pro rata share of the pool by counted weight
+        let reward = (ctx.accounts.reward_pool.total_amount as u128)
+            .checked_mul(vote_record.weight as u128)
+            .ok_or(GovernanceError::ArithmeticError)?
+            / total_votes(proposal)? as u128;
+        let reward = reward as u64;
+        
+        vote_record.reward_claimed = true;
+        
+        let reward_pool = &mut ctx.accounts.reward_pool;
+        reward_pool.claimed_amount = reward_pool.claimed_amount
+            .checked_add(reward)
+            .ok_or(GovernanceError::ArithmeticError)?;
+        
+        let pool_info = reward_pool.to_account_info();
+        let voter_info = ctx.accounts.voter.to_account_info();
+        **pool_info.try_borrow_mut_lamports()? = pool_info
+            .lamports()
+            .checked_sub(reward)
+            .ok_or(GovernanceError::ArithmeticError)?;
+        **voter_info.try_borrow_mut_lamports()? = voter_info
+            .lamports()
+            .checked_add(reward)
+            .ok_or(GovernanceError::ArithmeticError)?;
+        
+        emit!(VoteRewardClaimedEvent {
+            dao: proposal.dao,
+            proposal: proposal.key(),
+            voter: ctx.accounts.voter.key(),
+            amount: reward,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Claimed {} lamports of vote rewards", reward);
+        
+        Ok(())
+    }
+
+    // Called by the treasury through an executed proposal to recover unclaimed rewards and rounding dust
+    pub fn sweep_vote_rewards(ctx: Context<SweepVoteRewards>) -> Result<()> {
+        require!(
+            !matches!(
+                ctx.accounts.proposal.state,
+                ProposalState::Draft | ProposalState::Voting
+            ),
+            GovernanceError::ProposalNotFinalized
+        );
+        
+        let reward_pool = &mut ctx.accounts.reward_pool;
+        require!(!reward_pool.swept, GovernanceError::RewardsSwept);
+        reward_pool.swept = true;
+        
+        // Everything above the pool's rent goes back, so the pool can be closed afterwards
+        let pool_info = reward_pool.to_account_info();
+        let treasury_info = ctx.accounts.treasury.to_account_info();
+        let rent_exempt = Rent::get()?.minimum_balance(pool_info.data_len());
+        let amount = pool_info.lamports().saturating_sub(rent_exempt);
+        **pool_info.try_borrow_mut_lamports()? -= amount;
+        **treasury_info.try_borrow_mut_lamports()? = treasury_info
+            .lamports()
+            .checked_add(amount)
+            .ok_or(GovernanceError::ArithmeticError)?;
+        
+        emit!(VoteRewardsSweptEvent {
+            dao: ctx.accounts.dao.key(),
+            proposal: reward_pool.proposal,
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Swept {} lamports of vote rewards to the treasury", amount);
+        
+        Ok(())
+    }
+
+    pub fn lock_tokens(ctx: Context<LockTokens>, amount: u64, lock_end: i64) -> Result<()> {
+        let vote_escrow = &mut ctx.accounts.vote_escrow;
+        let current_time = Clock::get()?.unix_timestamp;
+        
+        // A lock can be topped up or extended, but never shortened
+        require!(
+            lock_end > current_time
+                && lock_end >= vote_escrow.lock_end
+                && lock_end - current_time <= VoteEscrow::MAX_LOCK_DURATION,
+            GovernanceError::InvalidLockDuration
+        );
+        require!(
+            amount > 0 || lock_end > vote_escrow.lock_end,
+            GovernanceError::InvalidAmount
+        );
+        
+        if amount > 0 {
+            let transfer_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.owner_token_account.to_account_info(),
+                    to: ctx.accounts.escrow_vault.to_account_info(),
+                    authority: ctx.accounts.owner.to_account_info(),
+                },
+            );
+            token::transfer(transfer_ctx, amount)?;
+        }
+        
+        let (_, bump) = Pubkey::find_program_address(
+            &[
+                b"miya_escrow".as_ref(),
+                ctx.accounts.dao.key().as_ref(),
+                ctx.accounts.owner.key().as_ref(),
+            ],
+            ctx.program_id,
+        );
+        vote_escrow.dao = ctx.accounts.dao.key();
+        vote_escrow.owner = ctx.accounts.owner.key();
+        
+        // A new lock starts its history here. Changing an existing lock keeps its start
+        // and checkpoints the lock it replaces, so snapshots taken before the change keep
+        // their weight without gaining any from the tokens added after them.
+        if vote_escrow.amount == 0 {
+            vote_escrow.lock_start = current_time;
+            vote_escrow.history_len = 0;
+        } else {
+            vote_escrow.checkpoint();
+        }
+        vote_escrow.updated_at = current_time;
+        
+        vote_escrow.amount = vote_escrow.amount
+            .checked_add(amount)
+            .ok_or(GovernanceError::ArithmeticError)?;
+        vote_escrow.lock_end = lock_end;
+        vote_escrow.bump = bump;
+        
+        emit!(TokensLockedEvent {
+            dao: vote_escrow.dao,
+            owner: vote_escrow.owner,
+            amount,
+            locked_amount: vote_escrow.amount,
+            lock_end,
+            timestamp: current_time,
+        });
+        
+        msg!("Locked {} governance tokens until {}", amount, lock_end);
+        
+        Ok(())
+    }
+
+    pub fn unlock_tokens(ctx: Context<UnlockTokens>) -> Result<()> {
+        let vote_escrow = &mut ctx.accounts.vote_escrow;
+        let current_time = Clock::get()?.unix_timestamp;
+        
+        require!(current_time >= vote_escrow.lock_end, GovernanceError::LockNotExpired);
+        require!(vote_escrow.amount > 0, GovernanceError::InvalidAmount);
+        
+        // The escrow PDA owns the vault and signs the withdrawal
+        let amount = vote_escrow.amount;
+        let dao_key = vote_escrow.dao;
+        let owner_key = vote_escrow.owner;
+        let seeds = &[
+            b"miya_escrow".as_ref(),
+            dao_key.as_ref(),
+            owner_key.as_ref(),
+            &[vote_escrow.bump],
+        ];
+        let signer = &[&seeds[..]];
+        let transfer_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.escrow_vault.to_account_info(),
+                to: ctx.accounts.owner_token_account.to_account_info(),
+                authority: vote_escrow.to_account_info(),
+            },
+            signer,
+        );
+        token::transfer(transfer_ctx, amount)?;
+        
+        vote_escrow.amount = 0;
+        
+        emit!(TokensUnlockedEvent {
+            dao: dao_key,
+            owner: owner_key,
+            amount,
+            timestamp: current_time,
+        });
+        
+        msg!("Unlocked {} governance tokens", amount);
+        
+        Ok(())
+    }
+
+    pub fn cancel_proposal(ctx: Context<CancelProposal>) -> Result<()> {
+        let dao = &ctx.accounts.dao;
+        let proposal = &mut ctx.accounts.proposal;
+        
+        // Only drafts and proposals still in voting can be canceled
+        require!(
+            proposal.state == ProposalState::Draft || proposal.state == ProposalState::Voting,
+            GovernanceError::ProposalNotActive
+        );
+        
+        // Only the proposer or DAO authority can cancel
+        let is_proposer = ctx.accounts.canceler.key() == proposal.proposer;
+        let is_authority = ctx.accounts.canceler.key() == dao.authority;
+        require!(
+            is_proposer || is_authority,
+            GovernanceError::Unauthorized
+        );
+        
+        // Once voting has ended the outcome is settled by finalize_proposal, so the
+        // proposer cannot withdraw a failing proposal to recover the deposit
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(
+            !is_proposer || proposal.state == ProposalState::Draft || current_time < proposal.ends_at,
+            GovernanceError::VotingEnded
+        );
+        
+        // Mark proposal as canceled
+        proposal.state = ProposalState::Canceled;
+        
+        // Settle the deposit: slashed when the authority cancels someone else's
+        // proposal, refunded when the proposer withdraws it
+        if proposal.deposit_amount > 0 {
+            let slashed = is_authority && !is_proposer;
+            let recipient = if slashed {
+                ctx.accounts.treasury.to_account_info()
+            } else {
+                ctx.accounts.proposer.to_account_info()
+            };
+            let amount = release_deposit(proposal, &recipient)?;
+            
+            emit!(ProposalDepositSettledEvent {
+                dao: dao.key(),
+                proposal: proposal.key(),
+                recipient: recipient.key(),
+                amount,
+                slashed,
+                timestamp: current_time,
+            });
+        }
+        
+        emit!(ProposalCanceledEvent {
+            dao: dao.key(),
+            proposal: proposal.key(),
+            canceler: ctx.accounts.canceler.key(),
+            timestamp: current_time,
+        });
+        
+        msg!("Proposal canceled: {}", proposal.title);
+        
+        Ok(())
+    }
+
+    pub fn settle_proposal_deposit(ctx: Context<SettleProposalDeposit>) -> Result<()> {
+        let dao = &ctx.accounts.dao;
+        let proposal = &mut ctx.accounts.proposal;
+        
+        // The outcome is only known once the proposal has been finalized
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(
+            !matches!(
+                proposal.state,
+                ProposalState::Draft | ProposalState::Voting | ProposalState::Canceled
+            ),
+            GovernanceError::ProposalNotActive
+        );
+        require!(proposal.deposit_amount > 0, GovernanceError::NoDeposit);
+        
+        // Proposals that reached quorum get their deposit back, the rest are treated as spam
+        let slashed = !quorum_reached(dao, proposal)?;
+        let recipient = if slashed {
+            ctx.accounts.treasury.to_account_info()
+        } else {
+            ctx.accounts.proposer.to_account_info()
+        };
+        let amount = release_deposit(proposal, &recipient)?;
+        
+        emit!(ProposalDepositSettledEvent {
+            dao: dao.key(),
+            proposal: proposal.key(),
+            recipient: recipient.key(),
+            amount,
+            slashed,
+            timestamp: current_time,
+        });
+        
+        msg!("Proposal deposit settled: {}, slashed: {}", amount, slashed);
+        
+        Ok(())
+    }
+
+    pub fn close_vote_record(ctx: Context<CloseVoteRecord>) -> Result<()> {
+        // Records can be closed once the tally is final, or after the proposal itself was closed
+        let proposal_info = ctx.accounts.proposal.to_account_info();
+        if proposal_info.owner == ctx.program_id && !proposal_info.data_is_empty() {
+            let proposal = Proposal::try_deserialize(&mut &proposal_info.data.borrow()[..])?;
+            require!(
+                !matches!(proposal.state, ProposalState::Draft | ProposalState::Voting),
+                GovernanceError::ProposalNotFinalized
+            );
+        }
+        
+        // A voter's unclaimed reward would be lost with the record, unless the pool was swept
+        let pool_info = ctx.accounts.reward_pool.to_account_info();
+        if pool_info.owner == ctx.program_id && !pool_info.data_is_empty() {
+            let reward_pool = RewardPool::try_deserialize(&mut &pool_info.data.borrow()[..])?;
+            let vote_record = &ctx.accounts.vote_record;
+            require!(
+                !vote_record.has_voted || vote_record.reward_claimed || reward_pool.swept,
+                GovernanceError::RewardsUnclaimed
+            );
+        }
+        
+        msg!("Vote record closed");
+        
+        Ok(())
+    }
+
+    pub fn close_proposal(ctx: Context<CloseProposal>) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        
+        // Only proposals that can no longer change are closed, and only once the
+        // deposit has been refunded or slashed
+        require!(
+            matches!(
+                proposal.state,
+                ProposalState::Executed
+                    | ProposalState::Canceled
+                    | ProposalState::Defeated
+                    | ProposalState::Vetoed
+                    | ProposalState::Expired
+            ),
+            GovernanceError::ProposalNotClosable
+        );
+        require!(proposal.deposit_amount == 0, GovernanceError::DepositNotSettled);
+        // Action records hold their own rent and need the proposal to be closed
+        require!(proposal.action_count == 0, GovernanceError::ActionRecordsOpen);
+        
+        // A reward pool must be fully claimed or swept; what's left of it goes back to the treasury
+        let pool_info = ctx.accounts.reward_pool.to_account_info();
+        if pool_info.owner == ctx.program_id && !pool_info.data_is_empty() {
+            let reward_pool = Account::<RewardPool>::try_from(&pool_info)?;
+            require!(
+                reward_pool.swept || reward_pool.claimed_amount == reward_pool.total_amount,
+                GovernanceError::RewardsUnclaimed
+            );
+            reward_pool.close(ctx.accounts.treasury.to_account_info())?;
+        }
+        
+        emit!(ProposalClosedEvent {
+            dao: proposal.dao,
+            proposal: proposal.key(),
+            proposer: proposal.proposer,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Proposal closed: {}", proposal.title);
+        
+        Ok(())
+    }
+
+    pub fn update_dao_settings(
+        ctx: Context<UpdateDaoSettings>,
+        name: Option<String>,
+        min_voting_period: Option<i64>,
+        max_voting_period: Option<i64>,
+        min_proposal_weight: Option<u64>,
+        proposal_deposit: Option<u64>,
+        quorum_votes: Option<u64>,
+        execution_delay: Option<i64>,
+        voting_strategy: Option<VotingStrategy>,
+        reveal_period: Option<i64>,
+        anonymous_voting: Option<bool>,
+    ) -> Result<()> {
+        let dao = &mut ctx.accounts.dao;
+        
+        // Only the DAO authority can update settings
+        require!(
+            ctx.accounts.authority.key() == dao.authority,
+            GovernanceError::Unauthorized
+        );
+        
+        // Update provided settings
+        if let Some(new_name) = name {
+            dao.name = new_name;
+        }
+        
+        if let Some(new_min) = min_voting_period {
+            dao.min_voting_period = new_min;
+        }
+        
+        if let Some(new_max) = max_voting_period {
+            dao.max_voting_period = new_max;
+        }
+        
+        if let Some(new_min_weight) = min_proposal_weight {
+            dao.min_proposal_weight = new_min_weight;
+        }
+        
+        if let Some(new_deposit) = proposal_deposit {
+            dao.proposal_deposit = new_deposit;
+        }
+        
+        if let Some(new_quorum) = quorum_votes {
+            dao.quorum_votes = new_quorum;
+        }
+        
+        if let Some(new_delay) = execution_delay {
+            require!(new_delay >= 0, GovernanceError::InvalidExecutionDelay);
+            dao.execution_delay = new_delay;
+        }
+        
+        if let Some(new_strategy) = voting_strategy {
+            dao.voting_strategy = new_strategy;
+        }
+        
+        if let Some(new_reveal_period) = reveal_period {
+            require!(new_reveal_period >= 0, GovernanceError::InvalidRevealPeriod);
+            dao.reveal_period = new_reveal_period;
+        }
+        
+        if let Some(anonymous) = anonymous_voting {
+            dao.anonymous_voting = anonymous;
+        }
+        
+        // Validate voting periods
+        require!(
+            dao.min_voting_period <= dao.max_voting_period,
+            GovernanceError::InvalidVotingPeriod
+        );
+        
+        // A veto council needs a timelock long enough to act before execution
+        require!(
+            dao.veto_council.is_empty() || dao.execution_delay >= Dao::MIN_COUNCIL_EXECUTION_DELAY,
+            GovernanceError::InvalidExecutionDelay
+        );
+        
+        emit!(DaoUpdatedEvent {
+            dao: dao.key(),
+            authority: dao.authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("DAO settings updated: {}", dao.name);
+        
+        Ok(())
+    }
+
+    pub fn set_veto_council(
+        ctx: Context<UpdateDaoSettings>,
+        members: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        let dao = &mut ctx.accounts.dao;
+        
+        // Only the DAO authority can change the veto council
+        require!(
+            ctx.accounts.authority.key() == dao.authority,
+            GovernanceError::Unauthorized
+        );
+        
+        // An empty council with a zero threshold disables vetoes
+        require!(
+            members.len() <= Dao::MAX_VETO_COUNCIL
+                && threshold as usize <= members.len()
+                && (threshold > 0 || members.is_empty()),
+            GovernanceError::InvalidVetoCouncil
+        );
+        for (i, member) in members.iter().enumerate() {
+            require!(
+                !members[..i].contains(member),
+                GovernanceError::InvalidVetoCouncil
+            );
+        }
+        require!(
+            members.is_empty() || dao.execution_delay >= Dao::MIN_COUNCIL_EXECUTION_DELAY,
+            GovernanceError::InvalidExecutionDelay
+        );
+        
+        dao.veto_council = members;
+        dao.veto_threshold = threshold;
+        dao.veto_council_version = dao.veto_council_version.checked_add(1).ok_or(GovernanceError::ArithmeticError)?;
+        
+        emit!(VetoCouncilUpdatedEvent {
+            dao: dao.key(),
+            members: dao.veto_council.clone(),
+            threshold,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Veto council updated: {} members, threshold {}", dao.veto_council.len(), threshold);
+        
+        Ok(())
+    }
+
+    // While deactivated, in-flight proposals cannot be voted on, finalized, queued or
+    // executed, and no new proposals can be created. Their timers keep running.
+    // Cancellation, deposit settlement and vetoes remain available.
+    pub fn deactivate_dao(ctx: Context<SetDaoStatus>) -> Result<()> {
+        let dao = &mut ctx.accounts.dao;
+        let signer = ctx.accounts.signer.key();
+        
+        // The authority or the treasury, through an executed proposal, can deactivate the DAO
+        let treasury = Pubkey::create_program_address(
+            &[b"miya_treasury".as_ref(), dao.key().as_ref(), &[dao.treasury_bump]],
+            ctx.program_id,
+        )
+        .map_err(|_| GovernanceError::Unauthorized)?;
+        require!(
+            signer == dao.authority || signer == treasury,
+            GovernanceError::Unauthorized
+        );
+        require!(dao.is_active, GovernanceError::DaoInactive);
+        // Reactivation needs the authority to sign outside a proposal, which the treasury can't do
+        require!(dao.authority != treasury, GovernanceError::TreasuryCannotReactivate);
+        
+        dao.is_active = false;
+        
+        emit!(DaoStatusChangedEvent {
+            dao: dao.key(),
+            is_active: false,
+            changed_by: signer,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("DAO deactivated: {}", dao.name);
+        
+        Ok(())
+    }
+
+    // Proposals cannot execute while the DAO is inactive, so only the authority can reactivate it
+    pub fn reactivate_dao(ctx: Context<SetDaoStatus>) -> Result<()> {
+        let dao = &mut ctx.accounts.dao;
+        let signer = ctx.accounts.signer.key();
+        
+        require!(signer == dao.authority, GovernanceError::Unauthorized);
+        require!(!dao.is_active, GovernanceError::DaoAlreadyActive);
+        
+        dao.is_active = true;
+        
+        emit!(DaoStatusChangedEvent {
+            dao: dao.key(),
+            is_active: true,
+            changed_by: signer,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("DAO reactivated: {}", dao.name);
+        
+        Ok(())
+    }
+
+    pub fn initialize_voter_registry(
+        ctx: Context<InitializeVoterRegistry>,
+        verifier: Pubkey,
+        quorum_members: u64,
+    ) -> Result<()> {
+        let dao = &ctx.accounts.dao;
+        let registry = &mut ctx.accounts.voter_registry;
+        
+        // Only the DAO authority can set up the registry
+        require!(
+            ctx.accounts.authority.key() == dao.authority,
+            GovernanceError::Unauthorized
+        );
+        
+        registry.dao = dao.key();
+        registry.verifier = verifier;
+        registry.member_count = 0;
+        registry.quorum_members = quorum_members;
+        registry.filled_subtrees = [[0; 32]; VoterRegistry::DEPTH];
+        registry.root = VoterRegistry::empty_root();
+        
+        msg!("Voter registry initialized for DAO: {}", dao.name);
+        
+        Ok(())
+    }
+
+    pub fn register_voter(ctx: Context<RegisterVoter>, commitment: [u8; 32]) -> Result<()> {
+        let dao = &ctx.accounts.dao;
+        let registry = &mut ctx.accounts.voter_registry;
+        
+        // Only the DAO authority can admit members
+        require!(
+            ctx.accounts.authority.key() == dao.authority,
+            GovernanceError::Unauthorized
+        );
+        
+        let leaf_index = registry.insert(commitment)?;
+        
+        emit!(VoterRegisteredEvent {
+            dao: dao.key(),
+            commitment,
+            leaf_index,
+            root: registry.root,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Voter registered at index {}", leaf_index);
+        
+        Ok(())
+    }
+
+    pub fn set_voter_quorum(ctx: Context<RegisterVoter>, quorum_members: u64) -> Result<()> {
+        let dao = &ctx.accounts.dao;
+        
+        // Only the DAO authority can change the quorum; proposals keep the one they signed off with
+        require!(
+            ctx.accounts.authority.key() == dao.authority,
+            GovernanceError::Unauthorized
+        );
+        
+        ctx.accounts.voter_registry.quorum_members = quorum_members;
+        
+        msg!("Voter registry quorum set to {} members", quorum_members);
+        
+        Ok(())
+    }
+
+    pub fn transfer_dao_authority(
+        ctx: Context<TransferDaoAuthority>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        let dao = &mut ctx.accounts.dao;
+        
+        // Only the current DAO authority can transfer authority
+        require!(
+            ctx.accounts.authority.key() == dao.authority,
+            GovernanceError::Unauthorized
+        );
+        
+        // The transfer only completes once the new authority accepts it
+        dao.pending_authority = Some(new_authority);
+        
+        emit!(AuthorityTransferProposedEvent {
+            dao: dao.key(),
+            authority: dao.authority,
+            pending_authority: new_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("DAO authority transfer proposed to: {}", new_authority);
+        
+        Ok(())
+    }
+
+    pub fn accept_dao_authority(ctx: Context<AcceptDaoAuthority>) -> Result<()> {
+        let dao = &mut ctx.accounts.dao;
+        let new_authority = ctx.accounts.new_authority.key();
+        
+        // Only the pending authority can accept the transfer
+        let pending_authority = dao.pending_authority.ok_or(GovernanceError::NoPendingAuthority)?;
+        require!(
+            new_authority == pending_authority,
+            GovernanceError::Unauthorized
+        );
+        
+        // Update the authority
+        let old_authority = dao.authority;
+        dao.authority = new_authority;
+        dao.pending_authority = None;
+        
+        emit!(AuthorityTransferredEvent {
+            dao: dao.key(),
+            old_authority,
+            new_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("DAO authority transferred to: {}", new_authority);
+        
+        Ok(())
+    }
+
+    pub fn cancel_dao_authority_transfer(ctx: Context<TransferDaoAuthority>) -> Result<()> {
+        let dao = &mut ctx.accounts.dao;
+        
+        // Only the current DAO authority can cancel a pending transfer
+        require!(
+            ctx.accounts.authority.key() == dao.authority,
+            GovernanceError::Unauthorized
+        );
+        
+        let pending_authority = dao.pending_authority.take().ok_or(GovernanceError::NoPendingAuthority)?;
+        
+        emit!(AuthorityTransferCanceledEvent {
+            dao: dao.key(),
+            authority: dao.authority,
+            pending_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("DAO authority transfer canceled");
+        
+        Ok(())
+    }
+}
+
+// Whether enough votes were cast for the outcome to count. Anonymous votes are
+// counted per member rather than by token weight, so they use the registry's quorum.
+fn quorum_reached(dao: &Dao, proposal: &Proposal) -> Result<bool> {
+    let quorum = if proposal.is_anonymous {
+        proposal.member_quorum
+    } else {
+        dao.quorum_votes
+    };
+    Ok(total_votes(proposal)? >= quorum)
+}
+
+// Total weight counted on a proposal, across every vote type
+fn total_votes(proposal: &Proposal) -> Result<u64> {
+    let total_votes = proposal.for_votes
+        .checked_add(proposal.against_votes)
+        .and_then(|votes| votes.checked_add(proposal.abstain_votes))
+        .ok_or(GovernanceError::ArithmeticError)?;
+    
+    Ok(total_votes)
+}
+
+// Move the escrowed deposit out of the proposal account and clear it
+fn release_deposit<'info>(
+    proposal: &mut Account<'info, Proposal>,
+    recipient: &AccountInfo<'info>,
+) -> Result<u64> {
+    let amount = proposal.deposit_amount;
+    proposal.deposit_amount = 0;
+    
+    let proposal_info = proposal.to_account_info();
+    **proposal_info.try_borrow_mut_lamports()? = proposal_info
+        .lamports()
+        .checked_sub(amount)
+        .ok_or(GovernanceError::ArithmeticError)?;
+    **recipient.try_borrow_mut_lamports()? = recipient
+        .lamports()
+        .checked_add(amount)
+        .ok_or(GovernanceError::ArithmeticError)?;
+    
+    Ok(amount)
+}
+
+// Commitment published during the commit phase: keccak(proposal || voter || vote || weight || salt).
+// Binding the proposal and voter stops a commitment from being copied by another
+// voter or replayed on another proposal.
+fn vote_commitment(
+    proposal: &Pubkey,
+    voter: &Pubkey,
+    vote: &Vote,
+    weight: u64,
+    salt: &[u8; 32],
+) -> Result<[u8; 32]> {
+    let vote_bytes = vote.try_to_vec()?;
+    Ok(keccak::hashv(&[
+        proposal.as_ref(),
+        voter.as_ref(),
+        &vote_bytes,
+        &weight.to_le_bytes(),
+        salt,
+    ])
+    .0)
+}
+
+// Floor of the square root, by Newton's method
+fn integer_sqrt(value: u64) -> Result<u64> {
+    if value < 2 {
+        return Ok(value);
+    }
+    
+    let value = value as u128;
+    let mut root = value;
+    let mut next = (root + 1) / 2;
+    while next < root {
+        root = next;
+        next = root
+            .checked_add(value / root)
+            .ok_or(GovernanceError::ArithmeticError)?
+            / 2;
+    }
+    
+    u64::try_from(root).map_err(|_| error!(GovernanceError::ArithmeticError))
+}
+
+// Add a voter's weight to the matching tally, checking the vote fits the proposal
+fn add_vote(proposal: &mut Proposal, vote: &Vote, weight: u64) -> Result<()> {
+    match vote {
+        Vote::For => {
+            require!(proposal.vote_type == VoteType::Binary, GovernanceError::InvalidVote);
+            proposal.for_votes = proposal.for_votes.checked_add(weight).ok_or(GovernanceError::ArithmeticError)?;
+        },
+        Vote::Against => {
+            proposal.against_votes = proposal.against_votes.checked_add(weight).ok_or(GovernanceError::ArithmeticError)?;
+        },
+        Vote::Abstain => {
+            proposal.abstain_votes = proposal.abstain_votes.checked_add(weight).ok_or(GovernanceError::ArithmeticError)?;
+        },
+        Vote::Choice(choices) => {
+            let max_choices = match proposal.vote_type {
+                VoteType::Binary => 0,
+                VoteType::SingleChoice => 1,
+                VoteType::Approval => proposal.options.len(),
+            };
+            require!(
+                !choices.is_empty() && choices.len() <= max_choices,
+                GovernanceError::InvalidVote
+            );
+            for (i, choice) in choices.iter().enumerate() {
+                require!(!choices[..i].contains(choice), GovernanceError::InvalidVote);
+                let option = proposal
+                    .options
+                    .get_mut(*choice as usize)
+                    .ok_or(GovernanceError::InvalidVote)?;
+                // Every chosen option receives the voter's full weight
+                option.vote_weight = option.vote_weight.checked_add(weight).ok_or(GovernanceError::ArithmeticError)?;
+            }
+            proposal.for_votes = proposal.for_votes.checked_add(weight).ok_or(GovernanceError::ArithmeticError)?;
+        },
+    }
+    
+    Ok(())
+}
+
+// Remove a previously counted weight from the matching tally
+fn remove_vote(proposal: &mut Proposal, vote: &Vote, weight: u64) -> Result<()> {
+    match vote {
+        Vote::For => {
+            proposal.for_votes = proposal.for_votes.checked_sub(weight).ok_or(GovernanceError::ArithmeticError)?;
+        },
+        Vote::Against => {
+            proposal.against_votes = proposal.against_votes.checked_sub(weight).ok_or(GovernanceError::ArithmeticError)?;
+        },
+        Vote::Abstain => {
+            proposal.abstain_votes = proposal.abstain_votes.checked_sub(weight).ok_or(GovernanceError::ArithmeticError)?;
+        },
+        Vote::Choice(choices) => {
+            for choice in choices.iter() {
+                let option = proposal
+                    .options
+                    .get_mut(*choice as usize)
+                    .ok_or(GovernanceError::InvalidVote)?;
+                option.vote_weight = option.vote_weight.checked_sub(weight).ok_or(GovernanceError::ArithmeticError)?;
+            }
+            proposal.for_votes = proposal.for_votes.checked_sub(weight).ok_or(GovernanceError::ArithmeticError)?;
+        },
+    }
+    
+    Ok(())
+}
+
+// The option whose actions run if the proposal passes. Binary proposals use option 0
+// when For beats Against; multiple-choice proposals need a single top option that
+// also beats Against, ties defeat the proposal.
+fn find_winning_option(proposal: &Proposal) -> Option<u8> {
+    if proposal.vote_type == VoteType::Binary {
+        return (proposal.for_votes > proposal.against_votes).then_some(0);
+    }
+    
+    let top_weight = proposal.options.iter().map(|option| option.vote_weight).max()?;
+    let mut leaders = proposal
+        .options
+        .iter()
+        .enumerate()
+        .filter(|(_, option)| option.vote_weight == top_weight);
+    let (index, _) = leaders.next()?;
+    
+    if leaders.next().is_some() || top_weight <= proposal.against_votes {
+        return None;
+    }
+    
+    Some(index as u8)
+}
+
+// Number of actions attached to the winning option
+fn winning_action_count(proposal: &Proposal) -> u16 {
+    match (proposal.vote_type, proposal.winning_option) {
+        (_, None) => 0,
+        (VoteType::Binary, Some(_)) => proposal.action_count,
+        (_, Some(index)) => proposal.options[index as usize].action_count,
+    }
+}
+
+// Attach an action to one of the proposal's options
+fn add_option_action(proposal: &mut Proposal, option_index: u8) -> Result<()> {
+    if proposal.vote_type == VoteType::Binary {
+        require!(option_index == 0, GovernanceError::InvalidOptions);
+    } else {
+        let option = proposal
+            .options
+            .get_mut(option_index as usize)
+            .ok_or(GovernanceError::InvalidOptions)?;
+        option.action_count = option.action_count.checked_add(1).ok_or(GovernanceError::ArithmeticError)?;
+    }
+    
+    Ok(())
+}
+
+// Instruction data for an Anchor program: the first 8 bytes of
+// sha256("global:<instruction name>") followed by the borsh-encoded arguments
+fn anchor_instruction_data(name: &str, args: &[u8]) -> Vec<u8> {
+    let mut data = hash(format!("global:{}", name).as_bytes()).to_bytes()[..8].to_vec();
+    data.extend_from_slice(args);
+    data
+}
+
+// Check a proof with the zk engine's verify_proof and read back its boolean result
+fn verify_with_zkengine<'info>(
+    verifier: &AccountInfo<'info>,
+    signer: &AccountInfo<'info>,
+    zkengine_program: &AccountInfo<'info>,
+    proof: Vec<u8>,
+    public_inputs: Vec<u8>,
+) -> Result<bool> {
+    let instruction = Instruction {
+        program_id: ZKENGINE_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(verifier.key(), false),
+            AccountMeta::new_readonly(signer.key(), true),
+        ],
+        data: anchor_instruction_data("verify_proof", &(proof, public_inputs).try_to_vec()?),
+    };
+    invoke(&instruction, &[verifier.clone(), signer.clone(), zkengine_program.clone()])?;
+    
+    let (program_id, data) = get_return_data().ok_or(GovernanceError::InvalidVoteProof)?;
+    require_keys_eq!(program_id, ZKENGINE_PROGRAM_ID, GovernanceError::InvalidVoteProof);
+    
+    Ok(bool::try_from_slice(&data)?)
+}
+
+// Invoke a single proposal action, letting the treasury PDA sign for it
+fn execute_action<'info>(
+    action: &ProposalAction,
+    treasury: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let mut account_infos = Vec::with_capacity(action.accounts.len() + 1);
+    let mut metas = Vec::with_capacity(action.accounts.len());
+    
+    for account in action.accounts.iter() {
+        let info = std::iter::once(treasury)
+            .chain(remaining_accounts.iter())
+            .find(|info| info.key == &account.pubkey)
+            .ok_or(GovernanceError::MissingActionAccount)?;
+        account_infos.push(info.clone());
+        metas.push(if account.is_writable {
+            AccountMeta::new(account.pubkey, account.is_signer)
+        } else {
+            AccountMeta::new_readonly(account.pubkey, account.is_signer)
+        });
+    }
+    
+    let program = remaining_accounts
+        .iter()
+        .find(|info| info.key == &action.program_id)
+        .ok_or(GovernanceError::MissingActionAccount)?;
+    account_infos.push(program.clone());
+    
+    let instruction = Instruction {
+        program_id: action.program_id,
+        accounts: metas,
+        data: action.data.clone(),
+    };
+    invoke_signed(&instruction, &account_infos, signer_seeds)?;
+    
+    Ok(())
+}
+
+struct TreasuryBalance {
+    account: Pubkey,
+    mint: Option<Pubkey>,
+    balance: u64,
+}
+
+// Collect the treasury's lamport balance and the balances of any treasury-owned
+// token accounts passed to the instruction
+fn treasury_balances(
+    treasury: &AccountInfo,
+    remaining_accounts: &[AccountInfo],
+) -> Result<Vec<TreasuryBalance>> {
+    let mut balances = vec![TreasuryBalance {
+        account: treasury.key(),
+        mint: None,
+        balance: treasury.lamports(),
+    }];
+    
+    for info in remaining_accounts.iter() {
+        if info.owner != &token::ID {
+            continue;
+        }
+        let data = info.try_borrow_data()?;
+        if let Ok(token_account) = TokenAccount::try_deserialize(&mut &data[..]) {
+            if token_account.owner == treasury.key() {
+                balances.push(TreasuryBalance {
+                    account: info.key(),
+                    mint: Some(token_account.mint),
+                    balance: token_account.amount,
+                });
+            }
+        }
+    }
+    
+    Ok(balances)
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct InitializeDao<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Dao::LEN,
+        seeds = [b"miya_dao", name.as_bytes()],
+        bump
+    )]
+    pub dao: Account<'info, Dao>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub governance_mint: Account<'info, Mint>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    title: String,
+    content_uri: String,
+    content_hash: [u8; 32],
+    voting_period: i64,
+    vote_type: VoteType,
+    options: Vec<String>
+)]
+pub struct CreateProposal<'info> {
+    #[account(
+        mut,
         seeds = [b"miya_dao", dao.name.as_bytes()],
         bump
     )]
     pub dao: Account<'info, Dao>,
     
     #[account(
-        init,
-        payer = proposer,
-        space = 8 + Proposal::LEN,
+        init,
+        payer = proposer,
+        space = 8 + Proposal::space(&title, &content_uri, &options),
+        seeds = [
+            b"miya_proposal",
+            dao.key().as_ref(),
+            &dao.proposal_count.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub proposal: Account<'info, Proposal>,
+    
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    
+    /// Proposer's lock, required only while the DAO has a proposal threshold
+    #[account(
+        seeds = [b"miya_escrow", dao.key().as_ref(), proposer.key().as_ref()],
+        bump = vote_escrow.bump
+    )]
+    pub vote_escrow: Option<Account<'info, VoteEscrow>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CastVote<'info> {
+    #[account(address = proposal.dao)]
+    pub dao: Account<'info, Dao>,
+    
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+    
+    #[account(mut)]
+    pub voter: Signer<'info>,
+    
+    #[account(
+        init,
+        payer = voter,
+        space = 8 + VoteRecord::LEN,
+        seeds = [
+            b"miya_vote",
+            proposal.key().as_ref(),
+            voter.key().as_ref(),
+        ],
+        bump
+    )]
+    pub vote_record: Account<'info, VoteRecord>,
+    
+    #[account(
+        seeds = [b"miya_escrow", proposal.dao.as_ref(), voter.key().as_ref()],
+        bump = vote_escrow.bump
+    )]
+    pub vote_escrow: Account<'info, VoteEscrow>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CommitVote<'info> {
+    #[account(address = proposal.dao)]
+    pub dao: Account<'info, Dao>,
+    
+    pub proposal: Account<'info, Proposal>,
+    
+    #[account(mut)]
+    pub voter: Signer<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = voter,
+        space = 8 + VoteRecord::LEN,
+        seeds = [
+            b"miya_vote",
+            proposal.key().as_ref(),
+            voter.key().as_ref(),
+        ],
+        bump
+    )]
+    pub vote_record: Account<'info, VoteRecord>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevealVote<'info> {
+    #[account(address = proposal.dao)]
+    pub dao: Account<'info, Dao>,
+    
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+    
+    pub voter: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"miya_vote",
+            proposal.key().as_ref(),
+            voter.key().as_ref(),
+        ],
+        bump,
+        constraint = vote_record.voter == voter.key() @ GovernanceError::Unauthorized
+    )]
+    pub vote_record: Account<'info, VoteRecord>,
+    
+    #[account(
+        seeds = [b"miya_escrow", proposal.dao.as_ref(), voter.key().as_ref()],
+        bump = vote_escrow.bump
+    )]
+    pub vote_escrow: Account<'info, VoteEscrow>,
+}
+
+#[derive(Accounts)]
+#[instruction(vote: Vote, nullifier: [u8; 32])]
+pub struct CastAnonymousVote<'info> {
+    #[account(address = proposal.dao)]
+    pub dao: Account<'info, Dao>,
+    
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+    
+    #[account(
+        seeds = [b"miya_voter_registry", proposal.dao.as_ref()],
+        bump,
+        has_one = verifier
+    )]
+    pub voter_registry: Account<'info, VoterRegistry>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + VoteNullifier::LEN,
+        seeds = [b"miya_vote_nullifier", proposal.key().as_ref(), nullifier.as_ref()],
+        bump
+    )]
+    pub vote_nullifier: Account<'info, VoteNullifier>,
+    
+    /// CHECK: zk engine verifier matched against the registry; the zk engine checks its contents
+    #[account(mut, owner = ZKENGINE_PROGRAM_ID)]
+    pub verifier: UncheckedAccount<'info>,
+    
+    /// Pays for the nullifier; any relayer can submit the vote so the member's wallet stays unlinked
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    /// CHECK: the zk engine program
+    #[account(address = ZKENGINE_PROGRAM_ID)]
+    pub zkengine_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ChangeVote<'info> {
+    #[account(address = proposal.dao)]
+    pub dao: Account<'info, Dao>,
+    
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+    
+    pub voter: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"miya_vote",
+            proposal.key().as_ref(),
+            voter.key().as_ref(),
+        ],
+        bump,
+        constraint = vote_record.voter == voter.key() @ GovernanceError::Unauthorized
+    )]
+    pub vote_record: Account<'info, VoteRecord>,
+    
+    #[account(
+        seeds = [b"miya_escrow", proposal.dao.as_ref(), voter.key().as_ref()],
+        bump = vote_escrow.bump
+    )]
+    pub vote_escrow: Account<'info, VoteEscrow>,
+}
+
+#[derive(Accounts)]
+pub struct RelinquishVote<'info> {
+    #[account(address = proposal.dao)]
+    pub dao: Account<'info, Dao>,
+    
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+    
+    pub voter: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"miya_vote",
+            proposal.key().as_ref(),
+            voter.key().as_ref(),
+        ],
+        bump,
+        constraint = vote_record.voter == voter.key() @ GovernanceError::Unauthorized
+    )]
+    pub vote_record: Account<'info, VoteRecord>,
+}
+
+#[derive(Accounts)]
+#[instruction(option_index: u8, action: ProposalAction)]
+pub struct AddProposalAction<'info> {
+    #[account(
+        mut,
+        constraint = proposal.proposer == proposer.key() @ GovernanceError::Unauthorized
+    )]
+    pub proposal: Account<'info, Proposal>,
+    
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + ProposalActionRecord::space(&action),
+        seeds = [
+            b"miya_proposal_action",
+            proposal.key().as_ref(),
+            &proposal.next_action_index.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub action_record: Account<'info, ProposalActionRecord>,
+    
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(option_index: u8, protocol_action: ProtocolAction)]
+pub struct AddProtocolAction<'info> {
+    pub dao: Account<'info, Dao>,
+    
+    #[account(
+        mut,
+        constraint = proposal.dao == dao.key(),
+        constraint = proposal.proposer == proposer.key() @ GovernanceError::Unauthorized
+    )]
+    pub proposal: Account<'info, Proposal>,
+    
+    #[account(
+        seeds = [b"miya_treasury", dao.key().as_ref()],
+        bump = dao.treasury_bump
+    )]
+    pub treasury: SystemAccount<'info>,
+    
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + ProposalActionRecord::space(&protocol_action.to_proposal_action(dao.key(), treasury.key())),
+        seeds = [
+            b"miya_proposal_action",
+            proposal.key().as_ref(),
+            &proposal.next_action_index.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub action_record: Account<'info, ProposalActionRecord>,
+    
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveProposalAction<'info> {
+    #[account(
+        mut,
+        constraint = proposal.proposer == proposer.key() @ GovernanceError::Unauthorized
+    )]
+    pub proposal: Account<'info, Proposal>,
+    
+    #[account(
+        mut,
+        close = proposer,
+        constraint = action_record.proposal == proposal.key()
+    )]
+    pub action_record: Account<'info, ProposalActionRecord>,
+    
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseActionRecord<'info> {
+    #[account(
+        mut,
+        constraint = proposal.proposer == proposer.key() @ GovernanceError::Unauthorized
+    )]
+    pub proposal: Account<'info, Proposal>,
+    
+    #[account(
+        mut,
+        close = proposer,
+        constraint = action_record.proposal == proposal.key()
+    )]
+    pub action_record: Account<'info, ProposalActionRecord>,
+    
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SignOffProposal<'info> {
+    pub dao: Account<'info, Dao>,
+    
+    #[account(
+        mut,
+        constraint = proposal.dao == dao.key(),
+        constraint = proposal.proposer == proposer.key() @ GovernanceError::Unauthorized
+    )]
+    pub proposal: Account<'info, Proposal>,
+    
+    pub proposer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeProposal<'info> {
+    pub dao: Account<'info, Dao>,
+    
+    #[account(
+        mut,
+        constraint = proposal.dao == dao.key()
+    )]
+    pub proposal: Account<'info, Proposal>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireProposal<'info> {
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+}
+
+#[derive(Accounts)]
+pub struct QueueProposal<'info> {
+    pub dao: Account<'info, Dao>,
+    
+    #[account(
+        mut,
+        constraint = proposal.dao == dao.key()
+    )]
+    pub proposal: Account<'info, Proposal>,
+}
+
+#[derive(Accounts)]
+pub struct VetoProposal<'info> {
+    pub dao: Account<'info, Dao>,
+    
+    #[account(
+        mut,
+        constraint = proposal.dao == dao.key()
+    )]
+    pub proposal: Account<'info, Proposal>,
+    
+    pub council_member: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteProposalAction<'info> {
+    pub dao: Account<'info, Dao>,
+    
+    #[account(
+        mut,
+        constraint = proposal.dao == dao.key()
+    )]
+    pub proposal: Account<'info, Proposal>,
+    
+    #[account(
+        mut,
+        constraint = action_record.proposal == proposal.key()
+    )]
+    pub action_record: Account<'info, ProposalActionRecord>,
+    
+    /// Data-less PDA that holds the DAO's lamports and signs proposal actions
+    #[account(
+        mut,
+        seeds = [b"miya_treasury", dao.key().as_ref()],
+        bump = dao.treasury_bump
+    )]
+    pub treasury: SystemAccount<'info>,
+    
+    pub executor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DepositToTreasury<'info> {
+    pub dao: Account<'info, Dao>,
+    
+    #[account(
+        mut,
+        seeds = [b"miya_treasury", dao.key().as_ref()],
+        bump = dao.treasury_bump
+    )]
+    pub treasury: SystemAccount<'info>,
+    
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositTokensToTreasury<'info> {
+    pub dao: Account<'info, Dao>,
+    
+    #[account(
+        seeds = [b"miya_treasury", dao.key().as_ref()],
+        bump = dao.treasury_bump
+    )]
+    pub treasury: SystemAccount<'info>,
+    
+    pub depositor: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = depositor_token_account.owner == depositor.key(),
+    )]
+    pub depositor_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = treasury_token_account.owner == treasury.key(),
+        constraint = treasury_token_account.mint == depositor_token_account.mint,
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct LockTokens<'info> {
+    pub dao: Account<'info, Dao>,
+    
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + VoteEscrow::LEN,
+        seeds = [b"miya_escrow", dao.key().as_ref(), owner.key().as_ref()],
+        bump
+    )]
+    pub vote_escrow: Account<'info, VoteEscrow>,
+    
+    #[account(
+        init_if_needed,
+        payer = owner,
+        token::mint = governance_mint,
+        token::authority = vote_escrow,
+        seeds = [b"miya_escrow_vault", vote_escrow.key().as_ref()],
+        bump
+    )]
+    pub escrow_vault: Account<'info, TokenAccount>,
+    
+    #[account(address = dao.governance_mint)]
+    pub governance_mint: Account<'info, Mint>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = owner_token_account.owner == owner.key(),
+        constraint = owner_token_account.mint == dao.governance_mint,
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct UnlockTokens<'info> {
+    pub dao: Account<'info, Dao>,
+    
+    #[account(
+        mut,
+        seeds = [b"miya_escrow", dao.key().as_ref(), owner.key().as_ref()],
+        bump = vote_escrow.bump,
+        has_one = owner @ GovernanceError::Unauthorized
+    )]
+    pub vote_escrow: Account<'info, VoteEscrow>,
+    
+    #[account(
+        mut,
+        seeds = [b"miya_escrow_vault", vote_escrow.key().as_ref()],
+        bump
+    )]
+    pub escrow_vault: Account<'info, TokenAccount>,
+    
+    pub owner: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = owner_token_account.owner == owner.key(),
+        constraint = owner_token_account.mint == dao.governance_mint,
+    )]
+    pub owner_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct FundVoteRewards<'info> {
+    pub dao: Account<'info, Dao>,
+    
+    #[account(constraint = proposal.dao == dao.key())]
+    pub proposal: Account<'info, Proposal>,
+    
+    #[account(
+        init_if_needed,
+        payer = treasury,
+        space = 8 + RewardPool::LEN,
+        seeds = [b"miya_reward_pool", proposal.key().as_ref()],
+        bump
+    )]
+    pub reward_pool: Account<'info, RewardPool>,
+    
+    /// Only an executed proposal can sign for the treasury
+    #[account(
+        mut,
+        seeds = [b"miya_treasury", dao.key().as_ref()],
+        bump = dao.treasury_bump
+    )]
+    pub treasury: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SweepVoteRewards<'info> {
+    pub dao: Account<'info, Dao>,
+    
+    #[account(constraint = proposal.dao == dao.key())]
+    pub proposal: Account<'info, Proposal>,
+    
+    #[account(
+        mut,
+        seeds = [b"miya_reward_pool", proposal.key().as_ref()],
+        bump
+    )]
+    pub reward_pool: Account<'info, RewardPool>,
+    
+    /// Only an executed proposal can sign for the treasury
+    #[account(
+        mut,
+        seeds = [b"miya_treasury", dao.key().as_ref()],
+        bump = dao.treasury_bump
+    )]
+    pub treasury: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimVoteReward<'info> {
+    pub proposal: Account<'info, Proposal>,
+    
+    #[account(
+        mut,
+        seeds = [b"miya_reward_pool", proposal.key().as_ref()],
+        bump
+    )]
+    pub reward_pool: Account<'info, RewardPool>,
+    
+    #[account(
+        mut,
         seeds = [
-            b"miya_proposal",
-            dao.key().as_ref(),
-            &dao.proposal_count.to_le_bytes(),
+            b"miya_vote",
+            proposal.key().as_ref(),
+            voter.key().as_ref(),
         ],
-        bump
+        bump,
+        constraint = vote_record.voter == voter.key() @ GovernanceError::Unauthorized
+    )]
+    pub vote_record: Account<'info, VoteRecord>,
+    
+    #[account(mut)]
+    pub voter: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelProposal<'info> {
+    pub dao: Account<'info, Dao>,
+    
+    #[account(
+        mut,
+        constraint = proposal.dao == dao.key()
+    )]
+    pub proposal: Account<'info, Proposal>,
+    
+    pub canceler: Signer<'info>,
+    
+    /// Receives the deposit when the authority cancels the proposal
+    #[account(
+        mut,
+        seeds = [b"miya_treasury", dao.key().as_ref()],
+        bump = dao.treasury_bump
+    )]
+    pub treasury: SystemAccount<'info>,
+    
+    /// Receives the deposit when the proposer cancels the proposal
+    #[account(
+        mut,
+        constraint = proposer.key() == proposal.proposer
+    )]
+    pub proposer: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SettleProposalDeposit<'info> {
+    pub dao: Account<'info, Dao>,
+    
+    #[account(
+        mut,
+        constraint = proposal.dao == dao.key()
+    )]
+    pub proposal: Account<'info, Proposal>,
+    
+    #[account(
+        mut,
+        seeds = [b"miya_treasury", dao.key().as_ref()],
+        bump = dao.treasury_bump
+    )]
+    pub treasury: SystemAccount<'info>,
+    
+    #[account(
+        mut,
+        constraint = proposer.key() == proposal.proposer
+    )]
+    pub proposer: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseVoteRecord<'info> {
+    /// CHECK: may already be closed; its state is checked in the instruction when it still exists
+    pub proposal: UncheckedAccount<'info>,
+    
+    /// CHECK: only exists if the proposal's voters were rewarded; checked in the instruction
+    #[account(seeds = [b"miya_reward_pool", proposal.key().as_ref()], bump)]
+    pub reward_pool: UncheckedAccount<'info>,
+    
+    #[account(
+        mut,
+        close = voter,
+        seeds = [
+            b"miya_vote",
+            proposal.key().as_ref(),
+            voter.key().as_ref(),
+        ],
+        bump,
+        constraint = vote_record.voter == voter.key() @ GovernanceError::Unauthorized
+    )]
+    pub vote_record: Account<'info, VoteRecord>,
+    
+    #[account(mut)]
+    pub voter: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseProposal<'info> {
+    #[account(
+        mut,
+        close = proposer,
+        constraint = proposal.proposer == proposer.key() @ GovernanceError::Unauthorized
     )]
     pub proposal: Account<'info, Proposal>,
     
+    /// CHECK: only exists if the proposal's voters were rewarded; closed in the instruction
+    #[account(mut, seeds = [b"miya_reward_pool", proposal.key().as_ref()], bump)]
+    pub reward_pool: UncheckedAccount<'info>,
+    
+    /// Receives what's left of the reward pool
+    #[account(mut, seeds = [b"miya_treasury", proposal.dao.as_ref()], bump)]
+    pub treasury: SystemAccount<'info>,
+    
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateDaoSettings<'info> {
+    #[account(mut)]
+    pub dao: Account<'info, Dao>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeVoterRegistry<'info> {
+    pub dao: Account<'info, Dao>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + VoterRegistry::LEN,
+        seeds = [b"miya_voter_registry", dao.key().as_ref()],
+        bump
+    )]
+    pub voter_registry: Account<'info, VoterRegistry>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterVoter<'info> {
+    pub dao: Account<'info, Dao>,
+    
+    #[account(
+        mut,
+        seeds = [b"miya_voter_registry", dao.key().as_ref()],
+        bump
+    )]
+    pub voter_registry: Account<'info, VoterRegistry>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetDaoStatus<'info> {
     #[account(mut)]
-    pub proposer: Signer<'info>,
+    pub dao: Account<'info, Dao>,
     
-    pub system_program: Program<'info, System>,
+    /// The DAO authority, or the treasury when executed through a proposal
+    pub signer: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct CastVote<'info> {
+pub struct TransferDaoAuthority<'info> {
     #[account(mut)]
-    pub proposal: Account<'info, Proposal>,
+    pub dao: Account<'info, Dao>,
     
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptDaoAuthority<'info> {
     #[account(mut)]
-    pub voter: Signer<'info>,
+    pub dao: Account<'info, Dao>,
     
-    #[account(
-        init,
-        payer = voter,
-        space = 8 + VoteRecord::LEN,
-        seeds = [
-            b"miya_vote",
-            proposal.key().as_ref(),
-            voter.key().as_ref(),
-        ],
-        bump
-    )]
-    pub vote_record: Account<'info, VoteRecord>,
+    pub new_authority: Signer<'info>,
+}
+
+#[account]
+pub struct Dao {
+    pub authority: Pubkey,
+    pub pending_authority: Option<Pubkey>, // Set while an authority transfer awaits acceptance
+    pub name: String,
+    pub min_voting_period: i64,
+    pub max_voting_period: i64,
+    pub proposal_count: u64,
+    pub is_active: bool,
+    pub treasury_bump: u8,
+    pub governance_mint: Pubkey,
+    pub min_proposal_weight: u64, // Voting power the proposer must have locked
+    pub proposal_deposit: u64,    // Refundable lamports escrowed per proposal
+    pub quorum_votes: u64,        // Minimum total votes for an outcome to count
+    pub execution_delay: i64,     // Timelock between queueing and execution
+    pub veto_council: Vec<Pubkey>,
+    pub veto_threshold: u8,       // Council approvals needed to veto
+    pub veto_council_version: u32,
+    pub voting_strategy: VotingStrategy, // Applied to proposals created after a change
+    pub reveal_period: i64,       // Commit-reveal voting when non-zero
+    pub anonymous_voting: bool,   // New proposals are voted on through the voter registry
+}
+
+impl Dao {
+    pub const MAX_VETO_COUNCIL: usize = 8; // Approvals are tracked in a u8 bitmap
+    pub const MIN_COUNCIL_EXECUTION_DELAY: i64 = 60 * 60; // Queued proposals stay vetoable for an hour
     
-    pub system_program: Program<'info, System>,
+    pub const LEN: usize = 32 + 33 + 36 + 8 + 8 + 8 + 1 + 1 + 32 + 8 + 8 + 8 + 8 + (4 + 32 * Self::MAX_VETO_COUNCIL) + 1 + 4 + 1 + 8 + 1;
+    // 36 bytes for String (4 + 32)
+}
+
+#[account]
+pub struct Proposal {
+    pub dao: Pubkey,
+    pub proposer: Pubkey,
+    pub title: String,
+    pub content_uri: String,        // Off-chain location of the full proposal text
+    pub content_hash: [u8; 32],     // Hash of the off-chain content
+    pub state: ProposalState,
+    pub vote_type: VoteType,
+    pub voting_strategy: VotingStrategy, // Copied from the DAO at creation
+    pub reveal_period: i64,         // Copied from the DAO at creation; zero for public voting
+    pub is_anonymous: bool,         // Copied from the DAO at creation
+    pub voter_root: [u8; 32],       // Voter registry root snapshotted at sign-off
+    pub member_quorum: u64,         // Registry quorum snapshotted at sign-off for anonymous proposals
+    pub options: Vec<ProposalOption>, // Empty for binary proposals
+    pub winning_option: Option<u8>,   // Set when the proposal succeeds; 0 for binary proposals
+    pub action_count: u16,          // Actions currently attached to the proposal
+    pub next_action_index: u16,     // Index used for the next action PDA
+    pub executed_action_count: u16,
+    pub created_at: i64,
+    pub voting_period: i64,
+    pub voting_starts_at: i64,      // Set at sign-off
+    pub ends_at: i64,               // Set at sign-off
+    pub executable_at: i64,         // Set when queued
+    pub veto_approvals: u8,         // Bitmap over the DAO's veto council
+    pub veto_council_version: u32,
+    pub for_votes: u64,             // For multiple-choice proposals, weight that picked any option
+    pub against_votes: u64,
+    pub abstain_votes: u64,
+    pub id: u64,
+    pub deposit_amount: u64,
+}
+
+impl Proposal {
+    pub const MAX_TITLE_LEN: usize = 64;
+    pub const MAX_CONTENT_URI_LEN: usize = 200;
+    pub const MAX_OPTIONS: usize = 10;
+    
+    // How long a passed proposal stays executable
+    pub const EXECUTION_WINDOW: i64 = 14 * 24 * 60 * 60;
+    
+    // Everything except the title, content URI and options
+    pub const FIXED_LEN: usize = 32 + 32 + 32 + 1 + 1 + 1 + 8 + 1 + 32 + 8 + 2 + 2 + 2 + 2 + 8 + 8 + 8 + 8 + 8 + 1 + 4 + 8 + 8 + 8 + 8 + 8;
+    
+    // Exact space for a proposal built from these inputs
+    pub fn space(title: &str, content_uri: &str, options: &[String]) -> usize {
+        Self::FIXED_LEN
+            + 4 + title.len()
+            + 4 + content_uri.len()
+            + 4 + options.iter().map(|label| ProposalOption::space(label)).sum::<usize>()
+    }
+    
+    // Reveals close this long after voting; equal to ends_at for public voting
+    pub fn reveal_ends_at(&self) -> Result<i64> {
+        Ok(self.ends_at.checked_add(self.reveal_period).ok_or(GovernanceError::ArithmeticError)?)
+    }
+    
+    // Whether a passed proposal has outlived its execution window
+    pub fn is_expired(&self, now: i64) -> bool {
+        let executable_from = match self.state {
+            ProposalState::Succeeded => self.ends_at.saturating_add(self.reveal_period),
+            ProposalState::Queued | ProposalState::Executing => self.executable_at,
+            _ => return false,
+        };
+        now >= executable_from.saturating_add(Self::EXECUTION_WINDOW)
+    }
+    
+    // A passed proposal can only be queued within its execution window
+    pub fn queue(&mut self, now: i64, execution_delay: i64) -> Result<()> {
+        require!(
+            self.state == ProposalState::Succeeded,
+            GovernanceError::ProposalNotSucceeded
+        );
+        require!(!self.is_expired(now), GovernanceError::ProposalExpired);
+        
+        self.executable_at = now.checked_add(execution_delay).ok_or(GovernanceError::ArithmeticError)?;
+        self.state = ProposalState::Queued;
+        
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ProposalOption {
+    pub label: String,
+    pub vote_weight: u64,
+    pub action_count: u16,
+}
+
+impl ProposalOption {
+    pub const MAX_LABEL_LEN: usize = 32;
+    
+    pub fn space(label: &str) -> usize {
+        4 + label.len() + 8 + 2
+    }
+}
+
+#[account]
+pub struct ProposalActionRecord {
+    pub proposal: Pubkey,
+    pub index: u16,
+    pub option_index: u8,           // Option whose win runs this action; 0 for binary proposals
+    pub action: ProposalAction,
+    pub is_executed: bool,
+}
+
+impl ProposalActionRecord {
+    pub fn space(action: &ProposalAction) -> usize {
+        32 + 2 + 1 + action.size() + 1
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProposalState {
+    Draft,
+    Voting,
+    Succeeded,
+    Defeated,
+    Queued,
+    Executing,
+    Executed,
+    Canceled,
+    Vetoed,
+    Expired,  // Passed but not executed within the execution window
+}
+
+#[account]
+pub struct VoteRecord {
+    pub proposal: Pubkey,
+    pub voter: Pubkey,
+    pub vote: Vote,
+    pub weight: u64,
+    pub has_voted: bool,               // Whether the vote is counted in the tally
+    pub commitment: Option<[u8; 32]>,  // Hidden vote awaiting reveal on commit-reveal proposals
+    pub timestamp: i64,
+    pub reward_claimed: bool,
+}
+
+impl VoteRecord {
+    // The vote is sized for a choice of every option (1 + 4 + MAX_OPTIONS)
+    pub const LEN: usize = 32 + 32 + (1 + 4 + Proposal::MAX_OPTIONS) + 8 + 1 + 33 + 8 + 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LockCheckpoint {
+    pub amount: u64,
+    pub lock_end: i64,
+    pub since: i64,
+}
+
+#[account]
+pub struct VoteEscrow {
+    pub dao: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,         // Governance tokens held in the escrow vault
+    pub lock_start: i64,     // Set when tokens are locked into an empty escrow
+    pub lock_end: i64,
+    pub updated_at: i64,     // Last top-up or extension
+    pub history: [LockCheckpoint; Self::HISTORY_LEN], // Locks replaced by top-ups, oldest first
+    pub history_len: u8,
+    pub bump: u8,
+}
+
+impl VoteEscrow {
+    pub const MAX_LOCK_DURATION: i64 = 4 * 365 * 24 * 60 * 60;
+    pub const HISTORY_LEN: usize = 8;
+    
+    pub const LEN: usize = 32 + 32 + 8 + 8 + 8 + 8 + 24 * Self::HISTORY_LEN + 1 + 1;
+    
+    // Record the lock in force before a top-up or extension, dropping the oldest
+    // checkpoint once the history is full
+    pub fn checkpoint(&mut self) {
+        let checkpoint = LockCheckpoint {
+            amount: self.amount,
+            lock_end: self.lock_end,
+            since: self.updated_at,
+        };
+        let len = self.history_len as usize;
+        if len == Self::HISTORY_LEN {
+            self.history.rotate_left(1);
+            self.history[len - 1] = checkpoint;
+        } else {
+            self.history[len] = checkpoint;
+            self.history_len += 1;
+        }
+    }
+    
+    // Voting power decays linearly from the full amount at the maximum lock
+    // duration to zero at unlock. A snapshot is weighed by the lock that was in
+    // force at the time, so tokens cannot be moved in once a vote has started.
+    // Snapshots older than the retained history count nothing.
+    pub fn voting_power_at(&self, timestamp: i64) -> Result<u64> {
+        if self.lock_start > timestamp {
+            return Ok(0);
+        }
+        let (amount, lock_end) = if timestamp >= self.updated_at {
+            (self.amount, self.lock_end)
+        } else {
+            match self.history[..self.history_len as usize]
+                .iter()
+                .rev()
+                .find(|checkpoint| checkpoint.since <= timestamp)
+            {
+                Some(checkpoint) => (checkpoint.amount, checkpoint.lock_end),
+                None => return Ok(0),
+            }
+        };
+        if timestamp >= lock_end {
+            return Ok(0);
+        }
+        
+        let remaining = (lock_end - timestamp) as u128;
+        let power = (amount as u128)
+            .checked_mul(remaining)
+            .ok_or(GovernanceError::ArithmeticError)?
+            / Self::MAX_LOCK_DURATION as u128;
+        
+        Ok(power as u64)
+    }
+}
+
+#[account]
+pub struct VoterRegistry {
+    pub dao: Pubkey,
+    pub verifier: Pubkey, // zk engine verifier for membership proofs
+    pub member_count: u32,
+    pub quorum_members: u64, // Anonymous votes needed for an outcome to count
+    pub root: [u8; 32],
+    pub filled_subtrees: [[u8; 32]; Self::DEPTH], // Rightmost filled node per level
+}
+
+impl VoterRegistry {
+    pub const DEPTH: usize = 16;
+    pub const MEMBER_WEIGHT: u64 = 1;
+    
+    pub const LEN: usize = 32 + 32 + 4 + 8 + 32 + 32 * Self::DEPTH;
+    
+    // Root of a tree whose leaves are all zero
+    pub fn empty_root() -> [u8; 32] {
+        let mut zero = [0u8; 32];
+        for _ in 0..Self::DEPTH {
+            zero = keccak::hashv(&[&zero, &zero]).0;
+        }
+        zero
+    }
+    
+    // Append a membership commitment to the incremental keccak Merkle tree
+    pub fn insert(&mut self, leaf: [u8; 32]) -> Result<u32> {
+        let leaf_index = self.member_count;
+        require!(
+            (leaf_index as usize) < (1 << Self::DEPTH),
+            GovernanceError::VoterRegistryFull
+        );
+        
+        let mut index = leaf_index;
+        let mut current = leaf;
+        let mut zero = [0u8; 32];
+        for level in 0..Self::DEPTH {
+            let (left, right) = if index % 2 == 0 {
+                self.filled_subtrees[level] = current;
+                (current, zero)
+            } else {
+                (self.filled_subtrees[level], current)
+            };
+            current = keccak::hashv(&[&left, &right]).0;
+            zero = keccak::hashv(&[&zero, &zero]).0;
+            index /= 2;
+        }
+        
+        self.root = current;
+        self.member_count = leaf_index.checked_add(1).ok_or(GovernanceError::ArithmeticError)?;
+        
+        Ok(leaf_index)
+    }
+}
+
+#[account]
+pub struct VoteNullifier {
+    pub proposal: Pubkey,
+    pub nullifier: [u8; 32],
+}
+
+impl VoteNullifier {
+    pub const LEN: usize = 32 + 32;
+}
+
+#[account]
+pub struct RewardPool {
+    pub proposal: Pubkey,
+    pub total_amount: u64,   // Lamports funded for voters, on top of the pool's rent
+    pub claimed_amount: u64,
+    pub swept: bool,         // Unclaimed lamports returned to the treasury; no further claims
+}
+
+impl RewardPool {
+    pub const LEN: usize = 32 + 8 + 8 + 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub enum Vote {
+    For,
+    Against,
+    Abstain,
+    Choice(Vec<u8>), // Option indices for multiple-choice proposals
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VoteType {
+    Binary,       // For / Against / Abstain
+    SingleChoice, // Exactly one option
+    Approval,     // Any number of options, each receiving the full weight
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VotingStrategy {
+    Linear,    // One token of voting power, one vote
+    Quadratic, // Square root of voting power, dampening large holders
+}
+
+impl VotingStrategy {
+    pub fn effective_weight(&self, voting_power: u64) -> Result<u64> {
+        match self {
+            VotingStrategy::Linear => Ok(voting_power),
+            VotingStrategy::Quadratic => integer_sqrt(voting_power),
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ProposalAction {
+    pub program_id: Pubkey,
+    pub accounts: Vec<ActionAccount>,
+    pub data: Vec<u8>,
+}
+
+impl ProposalAction {
+    pub fn size(&self) -> usize {
+        32 + 4 + self.accounts.len() * ActionAccount::LEN + 4 + self.data.len()
+    }
+    
+    fn new(program_id: Pubkey, accounts: Vec<AccountMeta>, data: Vec<u8>) -> Self {
+        Self {
+            program_id,
+            accounts: accounts
+                .into_iter()
+                .map(|meta| ActionAccount {
+                    pubkey: meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data,
+        }
+    }
+    
+    pub fn accept_pool_authority(pool: Pubkey, new_authority: Pubkey) -> Self {
+        Self::new(
+            MIXER_PROGRAM_ID,
+            vec![AccountMeta::new(pool, false), AccountMeta::new_readonly(new_authority, true)],
+            anchor_instruction_data("accept_pool_authority", &[]),
+        )
+    }
+    
+    pub fn pause_pool(pool: Pubkey, authority: Pubkey) -> Self {
+        Self::new(
+            MIXER_PROGRAM_ID,
+            vec![AccountMeta::new(pool, false), AccountMeta::new_readonly(authority, true)],
+            anchor_instruction_data("pause_pool", &[]),
+        )
+    }
+    
+    pub fn resume_pool(pool: Pubkey, authority: Pubkey) -> Self {
+        Self::new(
+            MIXER_PROGRAM_ID,
+            vec![AccountMeta::new(pool, false), AccountMeta::new_readonly(authority, true)],
+            anchor_instruction_data("resume_pool", &[]),
+        )
+    }
+    
+    pub fn accept_bridge_authority(new_authority: Pubkey) -> Self {
+        let (bridge, _) = Pubkey::find_program_address(&[b"miya_bridge"], &BRIDGE_PROGRAM_ID);
+        Self::new(
+            BRIDGE_PROGRAM_ID,
+            vec![AccountMeta::new(bridge, false), AccountMeta::new_readonly(new_authority, true)],
+            anchor_instruction_data("accept_bridge_authority", &[]),
+        )
+    }
+    
+    pub fn set_bridge_fee(token_pair: Pubkey, authority: Pubkey, fee_percentage: u16) -> Self {
+        let (bridge, _) = Pubkey::find_program_address(&[b"miya_bridge"], &BRIDGE_PROGRAM_ID);
+        Self::new(
+            BRIDGE_PROGRAM_ID,
+            vec![
+                AccountMeta::new_readonly(bridge, false),
+                AccountMeta::new(token_pair, false),
+                AccountMeta::new_readonly(authority, true),
+            ],
+            anchor_instruction_data("update_token_pair_fee", &fee_percentage.to_le_bytes()),
+        )
+    }
+    
+    pub fn accept_engine_authority(new_authority: Pubkey) -> Self {
+        let (engine, _) = Pubkey::find_program_address(&[b"miya_zkengine"], &ZKENGINE_PROGRAM_ID);
+        Self::new(
+            ZKENGINE_PROGRAM_ID,
+            vec![AccountMeta::new(engine, false), AccountMeta::new_readonly(new_authority, true)],
+            anchor_instruction_data("accept_engine_authority", &[]),
+        )
+    }
+    
+    pub fn register_verifier(verifier_key: Pubkey, verifier_type: VerifierType, authority: Pubkey) -> Self {
+        let (engine, _) = Pubkey::find_program_address(&[b"miya_zkengine"], &ZKENGINE_PROGRAM_ID);
+        let (verifier, _) = Pubkey::find_program_address(
+            &[b"miya_verifier", verifier_key.as_ref()],
+            &ZKENGINE_PROGRAM_ID,
+        );
+        // Borsh encodes the unit enum as its variant index
+        let args = [verifier_key.as_ref(), &[verifier_type as u8]].concat();
+        Self::new(
+            ZKENGINE_PROGRAM_ID,
+            vec![
+                AccountMeta::new(engine, false),
+                AccountMeta::new(verifier, false),
+                AccountMeta::new_readonly(verifier_key, false),
+                AccountMeta::new(authority, true),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            anchor_instruction_data("register_verifier", &args),
+        )
+    }
+    
+    pub fn fund_vote_rewards(dao: Pubkey, proposal: Pubkey, treasury: Pubkey, amount: u64) -> Self {
+        let (reward_pool, _) = Pubkey::find_program_address(
+            &[b"miya_reward_pool", proposal.as_ref()],
+            &crate::ID,
+        );
+        Self::new(
+            crate::ID,
+            crate::accounts::FundVoteRewards {
+                dao,
+                proposal,
+                reward_pool,
+                treasury,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            crate::instruction::FundVoteRewards { amount }.data(),
+        )
+    }
+    
+    pub fn sweep_vote_rewards(dao: Pubkey, proposal: Pubkey, treasury: Pubkey) -> Self {
+        let (reward_pool, _) = Pubkey::find_program_address(
+            &[b"miya_reward_pool", proposal.as_ref()],
+            &crate::ID,
+        );
+        Self::new(
+            crate::ID,
+            crate::accounts::SweepVoteRewards {
+                dao,
+                proposal,
+                reward_pool,
+                treasury,
+            }
+            .to_account_metas(None),
+            crate::instruction::SweepVoteRewards {}.data(),
+        )
+    }
+}
+
+// Typed admin actions on the other MIYA programs, executed with the DAO treasury as authority
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub enum ProtocolAction {
+    AcceptPoolAuthority { pool: Pubkey },
+    PausePool { pool: Pubkey },
+    ResumePool { pool: Pubkey },
+    AcceptBridgeAuthority,
+    SetBridgeFee { token_pair: Pubkey, fee_percentage: u16 },
+    AcceptEngineAuthority,
+    RegisterVerifier { verifier_key: Pubkey, verifier_type: VerifierType },
+    FundVoteRewards { proposal: Pubkey, amount: u64 },
+    SweepVoteRewards { proposal: Pubkey },
+}
+
+impl ProtocolAction {
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            ProtocolAction::SetBridgeFee { fee_percentage, .. } => {
+                require!(fee_percentage <= 1000, GovernanceError::InvalidProtocolAction);
+            },
+            ProtocolAction::FundVoteRewards { amount, .. } => {
+                require!(amount > 0, GovernanceError::InvalidProtocolAction);
+            },
+            _ => {},
+        }
+        
+        Ok(())
+    }
+    
+    pub fn to_proposal_action(&self, dao: Pubkey, treasury: Pubkey) -> ProposalAction {
+        match *self {
+            ProtocolAction::AcceptPoolAuthority { pool } => ProposalAction::accept_pool_authority(pool, treasury),
+            ProtocolAction::PausePool { pool } => ProposalAction::pause_pool(pool, treasury),
+            ProtocolAction::ResumePool { pool } => ProposalAction::resume_pool(pool, treasury),
+            ProtocolAction::AcceptBridgeAuthority => ProposalAction::accept_bridge_authority(treasury),
+            ProtocolAction::SetBridgeFee { token_pair, fee_percentage } => {
+                ProposalAction::set_bridge_fee(token_pair, treasury, fee_percentage)
+            },
+            ProtocolAction::AcceptEngineAuthority => ProposalAction::accept_engine_authority(treasury),
+            ProtocolAction::RegisterVerifier { verifier_key, verifier_type } => {
+                ProposalAction::register_verifier(verifier_key, verifier_type, treasury)
+            },
+            ProtocolAction::FundVoteRewards { proposal, amount } => {
+                ProposalAction::fund_vote_rewards(dao, proposal, treasury, amount)
+            },
+            ProtocolAction::SweepVoteRewards { proposal } => {
+                ProposalAction::sweep_vote_rewards(dao, proposal, treasury)
+            },
+        }
+    }
+}
+
+// Mirrors the zk engine's verifier types so they encode identically
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerifierType {
+    Groth16,
+    Plonk,
+    Bulletproofs,
+    Custom,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ActionAccount {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+impl ActionAccount {
+    pub const LEN: usize = 32 + 1 + 1;
+}
+
+#[event]
+pub struct ProposalCreatedEvent {
+    pub dao: Pubkey,
+    pub proposal: Pubkey,
+    pub proposer: Pubkey,
+    pub id: u64,
+    pub content_hash: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VoteCastEvent {
+    pub dao: Pubkey,
+    pub proposal: Pubkey,
+    pub voter: Pubkey,
+    pub vote: Vote,
+    pub weight: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VoteCommittedEvent {
+    pub dao: Pubkey,
+    pub proposal: Pubkey,
+    pub voter: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AnonymousVoteCastEvent {
+    pub dao: Pubkey,
+    pub proposal: Pubkey,
+    pub nullifier: [u8; 32],
+    pub vote: Vote,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct ExecuteProposal<'info> {
-    #[account(mut)]
-    pub proposal: Account<'info, Proposal>,
-    
-    pub executor: Signer<'info>,
+#[event]
+pub struct VoterRegisteredEvent {
+    pub dao: Pubkey,
+    pub commitment: [u8; 32],
+    pub leaf_index: u32,
+    pub root: [u8; 32],
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct CancelProposal<'info> {
-    pub dao: Account<'info, Dao>,
-    
-    #[account(
-        mut,
-        constraint = proposal.dao == dao.key()
-    )]
-    pub proposal: Account<'info, Proposal>,
-    
-    pub canceler: Signer<'info>,
+#[event]
+pub struct VoteChangedEvent {
+    pub dao: Pubkey,
+    pub proposal: Pubkey,
+    pub voter: Pubkey,
+    pub previous_vote: Option<Vote>, // None if the previous vote was relinquished
+    pub previous_weight: u64,
+    pub vote: Option<Vote>,          // None when the vote is relinquished
+    pub weight: u64,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct UpdateDaoSettings<'info> {
-    #[account(mut)]
-    pub dao: Account<'info, Dao>,
-    
-    pub authority: Signer<'info>,
+#[event]
+pub struct ProposalSignedOffEvent {
+    pub dao: Pubkey,
+    pub proposal: Pubkey,
+    pub voting_starts_at: i64,
+    pub ends_at: i64,
 }
 
-#[derive(Accounts)]
-pub struct TransferDaoAuthority<'info> {
-    #[account(mut)]
-    pub dao: Account<'info, Dao>,
-    
-    pub authority: Signer<'info>,
+#[event]
+pub struct ProposalFinalizedEvent {
+    pub dao: Pubkey,
+    pub proposal: Pubkey,
+    pub state: ProposalState,
+    pub winning_option: Option<u8>,
+    pub timestamp: i64,
 }
 
-#[account]
-pub struct Dao {
-    pub authority: Pubkey,
-    pub name: String,
-    pub min_voting_period: i64,
-    pub max_voting_period: i64,
-    pub proposal_count: u64,
-    pub is_active: bool,
+#[event]
+pub struct ProposalQueuedEvent {
+    pub dao: Pubkey,
+    pub proposal: Pubkey,
+    pub executable_at: i64,
+    pub timestamp: i64,
 }
 
-impl Dao {
-    pub const LEN: usize = 32 + 36 + 8 + 8 + 8 + 1; // 36 bytes for String (4 + 32)
+#[event]
+pub struct ProposalVetoedEvent {
+    pub dao: Pubkey,
+    pub proposal: Pubkey,
+    pub approvals: u8,
+    pub timestamp: i64,
 }
 
-#[account]
-pub struct Proposal {
+#[event]
+pub struct VetoCouncilUpdatedEvent {
     pub dao: Pubkey,
-    pub proposer: Pubkey,
-    pub title: String,
-    pub description: String,
-    pub actions: Vec<ProposalAction>,
-    pub created_at: i64,
-    pub ends_at: i64,
-    pub is_executed: bool,
-    pub is_canceled: bool,
-    pub for_votes: u64,
-    pub against_votes: u64,
-    pub abstain_votes: u64,
-    pub id: u64,
+    pub members: Vec<Pubkey>,
+    pub threshold: u8,
+    pub timestamp: i64,
 }
 
-impl Proposal {
-    pub const LEN: usize = 32 + 32 + 36 + 256 + 512 + 8 + 8 + 1 + 1 + 8 + 8 + 8 + 8;
-    // 36 bytes for title String (4 + 32)
-    // 256 bytes for description String (4 + 252)
-    // 512 bytes for actions Vector (4 + ~508)
+#[event]
+pub struct ProposalActionExecutedEvent {
+    pub dao: Pubkey,
+    pub proposal: Pubkey,
+    pub index: u16,
+    pub executor: Pubkey,
+    pub timestamp: i64,
 }
 
-#[account]
-pub struct VoteRecord {
+#[event]
+pub struct ProposalExecutedEvent {
+    pub dao: Pubkey,
     pub proposal: Pubkey,
-    pub voter: Pubkey,
-    pub vote: Vote,
-    pub weight: u64,
-    pub has_voted: bool,
+    pub executor: Pubkey,
     pub timestamp: i64,
 }
 
-impl VoteRecord {
-    pub const LEN: usize = 32 + 32 + 1 + 8 + 1 + 8;
+#[event]
+pub struct ProposalCanceledEvent {
+    pub dao: Pubkey,
+    pub proposal: Pubkey,
+    pub canceler: Pubkey,
+    pub timestamp: i64,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
-pub enum Vote {
-    For,
-    Against,
-    Abstain,
+#[event]
+pub struct TreasuryDepositEvent {
+    pub dao: Pubkey,
+    pub depositor: Pubkey,
+    pub mint: Option<Pubkey>, // None for lamports
+    pub amount: u64,
+    pub balance: u64,
+    pub timestamp: i64,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct ProposalAction {
-    pub program_id: Pubkey,
-    pub accounts: Vec<ActionAccount>,
-    pub data: Vec<u8>,
+#[event]
+pub struct TreasuryWithdrawalEvent {
+    pub dao: Pubkey,
+    pub proposal: Pubkey,
+    pub mint: Option<Pubkey>, // None for lamports
+    pub amount: u64,
+    pub balance: u64,
+    pub timestamp: i64,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct ActionAccount {
-    pub pubkey: Pubkey,
-    pub is_signer: bool,
-    pub is_writable: bool,
+#[event]
+pub struct TokensLockedEvent {
+    pub dao: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub locked_amount: u64,
+    pub lock_end: i64,
+    pub timestamp: i64,
 }
 
 #[event]
-pub struct ProposalCreatedEvent {
+pub struct TokensUnlockedEvent {
+    pub dao: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VoteRewardsFundedEvent {
     pub dao: Pubkey,
     pub proposal: Pubkey,
-    pub proposer: Pubkey,
-    pub id: u64,
+    pub amount: u64,
+    pub total_amount: u64,
     pub timestamp: i64,
 }
 
 #[event]
-pub struct VoteCastEvent {
+pub struct VoteRewardsSweptEvent {
+    pub dao: Pubkey,
+    pub proposal: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VoteRewardClaimedEvent {
     pub dao: Pubkey,
     pub proposal: Pubkey,
     pub voter: Pubkey,
-    pub vote: Vote,
-    pub weight: u64,
+    pub amount: u64,
     pub timestamp: i64,
 }
 
 #[event]
-pub struct ProposalExecutedEvent {
+pub struct ProposalExpiredEvent {
     pub dao: Pubkey,
     pub proposal: Pubkey,
-    pub executor: Pubkey,
     pub timestamp: i64,
 }
 
 #[event]
-pub struct ProposalCanceledEvent {
+pub struct ProposalClosedEvent {
     pub dao: Pubkey,
     pub proposal: Pubkey,
-    pub canceler: Pubkey,
+    pub proposer: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProposalDepositSettledEvent {
+    pub dao: Pubkey,
+    pub proposal: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub slashed: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DaoStatusChangedEvent {
+    pub dao: Pubkey,
+    pub is_active: bool,
+    pub changed_by: Pubkey,
     pub timestamp: i64,
 }
 
@@ -508,6 +3599,14 @@ pub struct DaoUpdatedEvent {
     pub timestamp: i64,
 }
 
+#[event]
+pub struct AuthorityTransferProposedEvent {
+    pub dao: Pubkey,
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+    pub timestamp: i64,
+}
+
 #[event]
 pub struct AuthorityTransferredEvent {
     pub dao: Pubkey,
@@ -516,6 +3615,14 @@ pub struct AuthorityTransferredEvent {
     pub timestamp: i64,
 }
 
+#[event]
+pub struct AuthorityTransferCanceledEvent {
+    pub dao: Pubkey,
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+    pub timestamp: i64,
+}
+
 #[error_code]
 pub enum GovernanceError {
     #[msg("Unauthorized operation")]
@@ -524,11 +3631,23 @@ pub enum GovernanceError {
     #[msg("DAO is not active")]
     DaoInactive,
     
+    #[msg("DAO is already active")]
+    DaoAlreadyActive,
+    
+    #[msg("DAO authority is the treasury, which could not reactivate the DAO")]
+    TreasuryCannotReactivate,
+    
     #[msg("Proposal title cannot be empty")]
     EmptyTitle,
     
-    #[msg("Proposal description cannot be empty")]
-    EmptyDescription,
+    #[msg("Proposal title is too long")]
+    TitleTooLong,
+    
+    #[msg("Proposal content URI cannot be empty")]
+    EmptyContentUri,
+    
+    #[msg("Proposal content URI is too long")]
+    ContentUriTooLong,
     
     #[msg("Proposal must have at least one action")]
     NoActions,
@@ -548,9 +3667,361 @@ pub enum GovernanceError {
     #[msg("Voter has already voted on this proposal")]
     AlreadyVoted,
     
+    #[msg("Voter has no counted vote on this proposal")]
+    NotVoted,
+    
     #[msg("Proposal was rejected")]
     ProposalRejected,
     
     #[msg("Arithmetic error")]
     ArithmeticError,
-} 
\ No newline at end of file
+    
+    #[msg("Amount must be greater than zero")]
+    InvalidAmount,
+    
+    #[msg("Account required by a proposal action was not provided")]
+    MissingActionAccount,
+    
+    #[msg("Proposer does not have enough voting power")]
+    ProposalThresholdNotMet,
+    
+    #[msg("Proposal did not reach quorum")]
+    QuorumNotReached,
+    
+    #[msg("Proposal has no deposit to settle")]
+    NoDeposit,
+    
+    #[msg("Proposal is not a draft")]
+    ProposalNotDraft,
+    
+    #[msg("Proposal action has already been executed")]
+    ActionAlreadyExecuted,
+    
+    #[msg("Proposal has not succeeded")]
+    ProposalNotSucceeded,
+    
+    #[msg("Proposal is not queued for execution")]
+    ProposalNotQueued,
+    
+    #[msg("Proposal timelock has not elapsed")]
+    TimelockNotElapsed,
+    
+    #[msg("Invalid execution delay")]
+    InvalidExecutionDelay,
+    
+    #[msg("Invalid veto council configuration")]
+    InvalidVetoCouncil,
+    
+    #[msg("Signer is not a member of the veto council")]
+    NotVetoCouncilMember,
+    
+    #[msg("Veto window has closed")]
+    VetoWindowClosed,
+    
+    #[msg("Council member has already approved this veto")]
+    AlreadyApprovedVeto,
+    
+    #[msg("No authority transfer is pending")]
+    NoPendingAuthority,
+    
+    #[msg("Invalid protocol action parameters")]
+    InvalidProtocolAction,
+    
+    #[msg("Invalid proposal options")]
+    InvalidOptions,
+    
+    #[msg("Vote does not match the proposal's vote type or options")]
+    InvalidVote,
+    
+    #[msg("Action does not belong to the winning option")]
+    ActionNotInWinningOption,
+    
+    #[msg("Invalid lock duration")]
+    InvalidLockDuration,
+    
+    #[msg("Tokens are still locked")]
+    LockNotExpired,
+    
+    #[msg("Voter has no voting power at the proposal snapshot")]
+    NoVotingPower,
+    
+    #[msg("Proposal uses commit-reveal voting")]
+    PrivateVotingRequired,
+    
+    #[msg("Proposal uses public voting")]
+    PublicVotingRequired,
+    
+    #[msg("Revealed vote does not match the commitment")]
+    InvalidReveal,
+    
+    #[msg("Reveal period has ended")]
+    RevealPeriodEnded,
+    
+    #[msg("Reveal period has not ended yet")]
+    RevealPeriodNotEnded,
+    
+    #[msg("Invalid reveal period")]
+    InvalidRevealPeriod,
+    
+    #[msg("Vote weight exceeds the voter's weight at the proposal snapshot")]
+    VoteWeightTooHigh,
+    
+    #[msg("Proposal uses anonymous voting")]
+    AnonymousVotingRequired,
+    
+    #[msg("Voter registry account was not provided")]
+    MissingVoterRegistry,
+    
+    #[msg("Voter registry is full")]
+    VoterRegistryFull,
+    
+    #[msg("Anonymous vote proof is invalid")]
+    InvalidVoteProof,
+    
+    #[msg("Proposal has not been finalized")]
+    ProposalNotFinalized,
+    
+    #[msg("Vote reward has already been claimed")]
+    RewardAlreadyClaimed,
+    
+    #[msg("Vote rewards can't be topped up once claims have started")]
+    RewardClaimsStarted,
+    
+    #[msg("Vote rewards have been swept to the treasury")]
+    RewardsSwept,
+    
+    #[msg("Proposal can only be closed once executed, canceled, defeated, vetoed or expired")]
+    ProposalNotClosable,
+    
+    #[msg("Proposal is still within its execution window")]
+    ProposalNotExpired,
+    
+    #[msg("Proposal was not executed within its execution window")]
+    ProposalExpired,
+    
+    #[msg("Proposal still has action records; close them first")]
+    ActionRecordsOpen,
+    
+    #[msg("Vote rewards must be claimed or swept first")]
+    RewardsUnclaimed,
+    
+    #[msg("Proposal deposit must be settled first")]
+    DepositNotSettled,
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn integer_sqrt_floors_the_root() {
+        let max_root = u32::MAX as u64;
+        let cases = [
+            (0, 0),
+            (1, 1),
+            (2, 1),
+            (3, 1),
+            (4, 2),
+            (5, 2),
+            (8, 2),
+            (9, 3),
+            (10, 3),
+            (999_999, 999),
+            (1_000_000, 1_000),
+            (1_000_001, 1_000),
+            (max_root * max_root - 1, max_root - 1),
+            (max_root * max_root, max_root),
+            (max_root * max_root + 1, max_root),
+            (u64::MAX, max_root),
+        ];
+        
+        for &(value, expected) in cases.iter() {
+            assert_eq!(integer_sqrt(value).unwrap(), expected, "integer_sqrt({})", value);
+        }
+    }
+    
+    fn proposal_in(state: ProposalState) -> Proposal {
+        Proposal {
+            dao: Pubkey::default(),
+            proposer: Pubkey::default(),
+            title: String::new(),
+            content_uri: String::new(),
+            content_hash: [0; 32],
+            state,
+            vote_type: VoteType::Binary,
+            voting_strategy: VotingStrategy::Linear,
+            reveal_period: 0,
+            is_anonymous: false,
+            voter_root: [0; 32],
+            member_quorum: 0,
+            options: Vec::new(),
+            winning_option: Some(0),
+            action_count: 1,
+            next_action_index: 1,
+            executed_action_count: 0,
+            created_at: 0,
+            voting_period: 100,
+            voting_starts_at: 0,
+            ends_at: 100,
+            executable_at: 1_000,
+            veto_approvals: 0,
+            veto_council_version: 0,
+            for_votes: 1,
+            against_votes: 0,
+            abstain_votes: 0,
+            id: 0,
+            deposit_amount: 0,
+        }
+    }
+    
+    #[test]
+    fn passed_proposals_expire_after_the_execution_window() {
+        let window = Proposal::EXECUTION_WINDOW;
+        let cases = [
+            (ProposalState::Succeeded, 100 + window - 1, false),
+            (ProposalState::Succeeded, 100 + window, true),
+            (ProposalState::Queued, 1_000 + window - 1, false),
+            (ProposalState::Queued, 1_000 + window, true),
+            (ProposalState::Executing, 1_000 + window, true),
+            (ProposalState::Executed, i64::MAX, false),
+            (ProposalState::Defeated, i64::MAX, false),
+            (ProposalState::Voting, i64::MAX, false),
+        ];
+        
+        for &(state, now, expired) in cases.iter() {
+            assert_eq!(proposal_in(state).is_expired(now), expired, "{:?} at {}", state, now);
+        }
+    }
+    
+    #[test]
+    fn proposals_queue_only_within_the_execution_window() {
+        let window = Proposal::EXECUTION_WINDOW;
+        
+        let mut proposal = proposal_in(ProposalState::Succeeded);
+        proposal.queue(100 + window - 1, 60).unwrap();
+        assert_eq!(proposal.state, ProposalState::Queued);
+        assert_eq!(proposal.executable_at, 100 + window + 59);
+        
+        let mut stale = proposal_in(ProposalState::Succeeded);
+        assert_eq!(stale.queue(100 + window, 60).unwrap_err(), error!(GovernanceError::ProposalExpired));
+        assert_eq!(stale.state, ProposalState::Succeeded);
+        
+        let mut defeated = proposal_in(ProposalState::Defeated);
+        assert_eq!(defeated.queue(100, 60).unwrap_err(), error!(GovernanceError::ProposalNotSucceeded));
+    }
+    
+    #[test]
+    fn proposal_space_matches_the_serialized_size() {
+        let longest_labels: Vec<String> = (0..Proposal::MAX_OPTIONS)
+            .map(|i| format!("{:>1$}", i, ProposalOption::MAX_LABEL_LEN))
+            .collect();
+        let cases = [
+            (String::new(), String::new(), Vec::new()),
+            ("Fund the audit".to_string(), "ipfs://miya/audit".to_string(), vec!["Yes".to_string(), "Later".to_string()]),
+            ("t".repeat(Proposal::MAX_TITLE_LEN), "u".repeat(Proposal::MAX_CONTENT_URI_LEN), longest_labels),
+        ];
+        
+        for (title, content_uri, labels) in cases.iter() {
+            let mut proposal = proposal_in(ProposalState::Succeeded);
+            proposal.title = title.clone();
+            proposal.content_uri = content_uri.clone();
+            proposal.options = labels
+                .iter()
+                .map(|label| ProposalOption { label: label.clone(), vote_weight: 0, action_count: 0 })
+                .collect();
+            
+            assert_eq!(
+                proposal.try_to_vec().unwrap().len(),
+                Proposal::space(title, content_uri, labels),
+                "{} options",
+                labels.len()
+            );
+        }
+    }
+    
+    // Top up a lock the way lock_tokens does
+    fn top_up(escrow: &mut VoteEscrow, at: i64, amount: u64) {
+        escrow.checkpoint();
+        escrow.updated_at = at;
+        escrow.amount += amount;
+    }
+    
+    fn power(amount: u64, lock_end: i64, at: i64) -> u64 {
+        (amount as u128 * (lock_end - at) as u128 / VoteEscrow::MAX_LOCK_DURATION as u128) as u64
+    }
+    
+    #[test]
+    fn snapshots_keep_the_lock_in_force_between_top_ups() {
+        let lock_end = VoteEscrow::MAX_LOCK_DURATION;
+        let mut escrow = VoteEscrow {
+            dao: Pubkey::default(),
+            owner: Pubkey::default(),
+            amount: 1_000,
+            lock_start: 0,
+            lock_end,
+            updated_at: 0,
+            history: [LockCheckpoint::default(); VoteEscrow::HISTORY_LEN],
+            history_len: 0,
+            bump: 0,
+        };
+        top_up(&mut escrow, 10, 500);
+        top_up(&mut escrow, 30, 500);
+        
+        let cases = [
+            (0, power(1_000, lock_end, 0)),
+            (9, power(1_000, lock_end, 9)),
+            (10, power(1_500, lock_end, 10)),
+            (20, power(1_500, lock_end, 20)),
+            (30, power(2_000, lock_end, 30)),
+            (40, power(2_000, lock_end, 40)),
+        ];
+        for &(at, expected) in cases.iter() {
+            assert_eq!(escrow.voting_power_at(at).unwrap(), expected, "power at {}", at);
+        }
+        
+        // Once the history is full the oldest locks are dropped and their
+        // snapshots count nothing
+        for i in 0..VoteEscrow::HISTORY_LEN as i64 {
+            top_up(&mut escrow, 100 + i, 1);
+        }
+        assert_eq!(escrow.history_len as usize, VoteEscrow::HISTORY_LEN);
+        assert_eq!(escrow.voting_power_at(20).unwrap(), 0);
+        assert_eq!(escrow.voting_power_at(30).unwrap(), power(2_000, lock_end, 30));
+        assert_eq!(escrow.voting_power_at(100).unwrap(), power(2_001, lock_end, 100));
+    }
+    
+    // Root of the full tree with the given leaves followed by zero leaves
+    fn naive_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+        let mut level = vec![[0u8; 32]; 1 << VoterRegistry::DEPTH];
+        level[..leaves.len()].copy_from_slice(leaves);
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| keccak::hashv(&[&pair[0], &pair[1]]).0)
+                .collect();
+        }
+        level[0]
+    }
+    
+    #[test]
+    fn voter_registry_insert_matches_full_tree() {
+        let mut registry = VoterRegistry {
+            dao: Pubkey::default(),
+            verifier: Pubkey::default(),
+            member_count: 0,
+            quorum_members: 0,
+            root: VoterRegistry::empty_root(),
+            filled_subtrees: [[0; 32]; VoterRegistry::DEPTH],
+        };
+        assert_eq!(registry.root, naive_root(&[]));
+        
+        let mut leaves = Vec::new();
+        for i in 1..=5u8 {
+            let leaf = keccak::hash(&[i]).0;
+            assert_eq!(registry.insert(leaf).unwrap(), (i - 1) as u32);
+            leaves.push(leaf);
+            assert_eq!(registry.root, naive_root(&leaves), "root after {} members", i);
+        }
+        assert_eq!(registry.member_count, 5);
+    }
+}
@@ -1,22 +1,173 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::keccak;
+use anchor_lang::solana_program::program::{get_return_data, invoke};
+use anchor_lang::system_program;
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+};
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
 
 declare_id!("Mixer111111111111111111111111111111111111111");
 
+// Basis point denominator for relayer fees
+pub const MAX_FEE_BPS: u16 = 10_000;
+
+// Withdrawal proofs are checked by the zk engine, as deployed in Anchor.toml
+pub const ZKENGINE_PROGRAM_ID: Pubkey = anchor_lang::solana_program::pubkey!("ZkEngine11111111111111111111111111111111111");
+
+// VerifierType::Groth16 as the zk engine serializes it, the only proving system accepted
+pub const GROTH16_VERIFIER_TYPE: u8 = 0;
+
 #[program]
 pub mod miya_mixer {
     use super::*;
 
+    pub fn initialize_mixer_config(ctx: Context<InitializeMixerConfig>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.authority = ctx.accounts.authority.key();
+        
+        let (_, bump) = Pubkey::find_program_address(&[b"miya_mixer_config".as_ref()], ctx.program_id);
+        config.bump = bump;
+        
+        msg!("Mixer config initialized");
+        
+        Ok(())
+    }
+
+    pub fn create_denomination_pools<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CreateDenominationPools<'info>>,
+        denominations: Vec<u64>,
+        verifier: Pubkey,
+    ) -> Result<()> {
+        validate_mint_extensions(&ctx.accounts.token_mint)?;
+        
+        // Each new pool is passed as a remaining account, in the same order as the denominations
+        require!(!denominations.is_empty(), MiyaError::InvalidDenominations);
+        require!(
+            ctx.remaining_accounts.len() == denominations.len(),
+            MiyaError::InvalidDenominations
+        );
+        
+        let token_mint = ctx.accounts.token_mint.key();
+        let registry = &mut ctx.accounts.registry;
+        if registry.token_mint == Pubkey::default() {
+            registry.token_mint = token_mint;
+            let (_, bump) = Pubkey::find_program_address(
+                &[b"miya_denominations".as_ref(), token_mint.as_ref()],
+                ctx.program_id,
+            );
+            registry.bump = bump;
+        }
+        require!(
+            registry.denominations.len() + denominations.len() <= DenominationRegistry::MAX_DENOMINATIONS,
+            MiyaError::TooManyDenominations
+        );
+        
+        let pool_space = 8 + Pool::LEN;
+        for (deposit_amount, pool_info) in denominations.iter().zip(ctx.remaining_accounts.iter()) {
+            require!(*deposit_amount > 0, MiyaError::InvalidDenominations);
+            require!(
+                !registry.denominations.contains(deposit_amount),
+                MiyaError::DuplicateDenomination
+            );
+            
+            let deposit_amount_bytes = deposit_amount.to_le_bytes();
+            let (pool_key, bump) = Pubkey::find_program_address(
+                &[b"miya_pool".as_ref(), token_mint.as_ref(), &deposit_amount_bytes],
+                ctx.program_id,
+            );
+            require_keys_eq!(pool_info.key(), pool_key, MiyaError::InvalidPoolAccount);
+            
+            let seeds = &[
+                b"miya_pool".as_ref(),
+                token_mint.as_ref(),
+                &deposit_amount_bytes,
+                &[bump],
+            ];
+            create_pda_account(
+                &ctx.accounts.system_program.to_account_info(),
+                &ctx.accounts.authority.to_account_info(),
+                pool_info,
+                &[&seeds[..]],
+                pool_space,
+                ctx.program_id,
+            )?;
+            
+            let pool = Pool {
+                authority: ctx.accounts.authority.key(),
+                pending_authority: None,
+                token_mint,
+                verifier,
+                deposit_amount: *deposit_amount,
+                total_deposits: 0,
+                total_withdrawals: 0,
+                next_leaf_index: 0,
+                anonymity_set: 0,
+                max_relayer_fee_bps: Pool::DEFAULT_MAX_RELAYER_FEE_BPS,
+                require_registered_relayer: false,
+                is_active: true,
+                bump,
+            };
+            pool.try_serialize(&mut &mut pool_info.try_borrow_mut_data()?[..])?;
+            
+            registry.denominations.push(*deposit_amount);
+            msg!("Pool created for deposit amount: {}", deposit_amount);
+        }
+        
+        emit!(DenominationPoolsCreatedEvent {
+            token_mint,
+            denominations,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    pub fn get_denomination_pools(ctx: Context<GetDenominationPools>) -> Result<Vec<DenominationPool>> {
+        let registry = &ctx.accounts.registry;
+        
+        Ok(registry
+            .denominations
+            .iter()
+            .map(|deposit_amount| DenominationPool {
+                deposit_amount: *deposit_amount,
+                pool: Pubkey::find_program_address(
+                    &[
+                        b"miya_pool".as_ref(),
+                        registry.token_mint.as_ref(),
+                        &deposit_amount.to_le_bytes(),
+                    ],
+                    ctx.program_id,
+                )
+                .0,
+            })
+            .collect())
+    }
+
     pub fn initialize_pool(
         ctx: Context<InitializePool>,
         token_mint: Pubkey,
         deposit_amount: u64,
+        verifier: Pubkey,
     ) -> Result<()> {
+        // Reject Token-2022 extensions that would break the fixed denomination
+        validate_mint_extensions(&ctx.accounts.token_mint)?;
+        
         let pool = &mut ctx.accounts.pool;
         pool.authority = ctx.accounts.authority.key();
+        pool.pending_authority = None;
         pool.token_mint = token_mint;
+        pool.verifier = verifier;
         pool.deposit_amount = deposit_amount;
         pool.total_deposits = 0;
+        pool.total_withdrawals = 0;
+        pool.next_leaf_index = 0;
+        pool.anonymity_set = 0;
+        pool.max_relayer_fee_bps = Pool::DEFAULT_MAX_RELAYER_FEE_BPS;
+        pool.require_registered_relayer = false;
         pool.is_active = true;
 
         // Create a unique bump seed for the pool PDA
@@ -36,6 +187,50 @@ pub mod miya_mixer {
         Ok(())
     }
 
+    pub fn initialize_native_pool(
+        ctx: Context<InitializeNativePool>,
+        deposit_amount: u64,
+        verifier: Pubkey,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        pool.authority = ctx.accounts.authority.key();
+        pool.pending_authority = None;
+        pool.token_mint = Pool::NATIVE_MINT;
+        pool.verifier = verifier;
+        pool.deposit_amount = deposit_amount;
+        pool.total_deposits = 0;
+        pool.total_withdrawals = 0;
+        pool.next_leaf_index = 0;
+        pool.anonymity_set = 0;
+        pool.max_relayer_fee_bps = Pool::DEFAULT_MAX_RELAYER_FEE_BPS;
+        pool.require_registered_relayer = false;
+        pool.is_active = true;
+
+        let (_, bump) = Pubkey::find_program_address(
+            &[
+                b"miya_pool".as_ref(),
+                Pool::NATIVE_MINT.as_ref(),
+                &deposit_amount.to_le_bytes(),
+            ],
+            ctx.program_id,
+        );
+        pool.bump = bump;
+
+        // Lamports are held in a program-owned vault so withdrawals can debit it directly
+        let vault = &mut ctx.accounts.vault;
+        vault.pool = pool.key();
+        let (_, vault_bump) = Pubkey::find_program_address(
+            &[b"miya_sol_vault".as_ref(), pool.key().as_ref()],
+            ctx.program_id,
+        );
+        vault.bump = vault_bump;
+
+        msg!("Native SOL pool initialized");
+        msg!("Deposit amount: {}", deposit_amount);
+
+        Ok(())
+    }
+
     pub fn deposit(ctx: Context<Deposit>, commitment: [u8; 32], nullifier_hash: [u8; 32]) -> Result<()> {
         let pool = &mut ctx.accounts.pool;
         
@@ -55,24 +250,35 @@ pub mod miya_mixer {
             timestamp: Clock::get()?.unix_timestamp,
         };
         
-        // Transfer tokens from user to pool account
+        // Transfer tokens from user to pool account, measuring what actually arrives
+        let vault_balance_before = ctx.accounts.pool_token_account.amount;
         let transfer_ctx = CpiContext::new(
             ctx.accounts.token_program.to_account_info(),
-            Transfer {
+            TransferChecked {
                 from: ctx.accounts.user_token_account.to_account_info(),
+                mint: ctx.accounts.token_mint.to_account_info(),
                 to: ctx.accounts.pool_token_account.to_account_info(),
                 authority: ctx.accounts.signer.to_account_info(),
             },
         );
-        token::transfer(transfer_ctx, pool.deposit_amount)?;
+        token_interface::transfer_checked(transfer_ctx, pool.deposit_amount, ctx.accounts.token_mint.decimals)?;
+        
+        ctx.accounts.pool_token_account.reload()?;
+        let received = ctx.accounts.pool_token_account.amount
+            .checked_sub(vault_balance_before)
+            .ok_or(MiyaError::ArithmeticError)?;
+        require!(received >= pool.deposit_amount, MiyaError::DepositAmountMismatch);
 
         // Update pool stats
-        pool.total_deposits += 1;
+        let leaf_index = pool.record_deposit()?;
+        check_vault_invariant(pool, ctx.accounts.pool_token_account.amount)?;
         
         // Emit deposit event
         emit!(DepositEvent {
             pool: pool.key(),
             commitment,
+            leaf_index,
+            anonymity_set: pool.anonymity_set,
             timestamp: deposit_note.timestamp,
         });
 
@@ -84,29 +290,38 @@ pub mod miya_mixer {
     pub fn withdraw(
         ctx: Context<Withdraw>,
         proof: Vec<u8>,
-        nullifier: [u8; 32],
-        recipient: Pubkey,
-        relayer: Option<Pubkey>,
-        fee: Option<u64>,
+        public_inputs: WithdrawPublicInputs,
     ) -> Result<()> {
         let pool = &mut ctx.accounts.pool;
         
         // Ensure the pool is active
         require!(pool.is_active, MiyaError::PoolInactive);
         
-        // Verify the provided proof (placeholder for actual zk-proof verification)
-        // In a real implementation, this would call a zkengine program or verify on-chain
-        require!(verify_proof(&proof, &nullifier, &recipient), MiyaError::InvalidProof);
+        // A fee or refund is only valid together with a relayer, and vice versa
+        public_inputs.validate_relayer()?;
+        
+        // The pool's verifier checks the proof against the committed public inputs
+        let verified = verify_with_zkengine(
+            &ctx.accounts.verifier.to_account_info(),
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.zkengine_program.to_account_info(),
+            proof,
+            public_inputs.to_bytes(),
+        )?;
+        require!(verified, MiyaError::InvalidProof);
+        
+        // The nullifier PDA was created by this instruction, so the note wasn't spent before
+        let pool_nullifier = &mut ctx.accounts.pool_nullifier;
+        pool_nullifier.pool = pool.key();
+        pool_nullifier.nullifier = public_inputs.nullifier;
         
-        // Ensure the nullifier hasn't been used before
-        require!(!is_nullifier_used(nullifier), MiyaError::NullifierAlreadyUsed);
+        // Enforce the pool's relayer policy before anything is paid out
+        check_relayer_fee(pool, public_inputs.relayer, public_inputs.fee, ctx.accounts.relayer_registration.as_ref())?;
         
         // Calculate the withdrawal amount
-        let withdraw_amount = if let Some(fee_amount) = fee {
-            pool.deposit_amount.checked_sub(fee_amount).ok_or(MiyaError::ArithmeticError)?
-        } else {
-            pool.deposit_amount
-        };
+        let withdraw_amount = pool.deposit_amount
+            .checked_sub(public_inputs.fee)
+            .ok_or(MiyaError::ArithmeticError)?;
         
         // Transfer tokens from pool to the recipient
         let seeds = &[
@@ -117,41 +332,170 @@ pub mod miya_mixer {
         ];
         let signer = &[&seeds[..]];
         
+        let decimals = ctx.accounts.token_mint.decimals;
         let transfer_ctx = CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
-            Transfer {
+            TransferChecked {
                 from: ctx.accounts.pool_token_account.to_account_info(),
+                mint: ctx.accounts.token_mint.to_account_info(),
                 to: ctx.accounts.recipient_token_account.to_account_info(),
                 authority: ctx.accounts.pool.to_account_info(),
             },
             signer,
         );
-        token::transfer(transfer_ctx, withdraw_amount)?;
-        
-        // Pay fee to relayer if specified
-        if let (Some(fee_amount), Some(_)) = (fee, relayer) {
-            if fee_amount > 0 {
-                let relayer_transfer_ctx = CpiContext::new_with_signer(
-                    ctx.accounts.token_program.to_account_info(),
-                    Transfer {
-                        from: ctx.accounts.pool_token_account.to_account_info(),
-                        to: ctx.accounts.relayer_token_account.to_account_info(),
-                        authority: ctx.accounts.pool.to_account_info(),
-                    },
-                    signer,
-                );
-                token::transfer(relayer_transfer_ctx, fee_amount)?;
-            }
+        token_interface::transfer_checked(transfer_ctx, withdraw_amount, decimals)?;
+        
+        // Pay the proven fee to the relayer
+        if public_inputs.fee > 0 {
+            let relayer_token_account = ctx.accounts.relayer_token_account
+                .as_ref()
+                .ok_or(MiyaError::MissingRelayerAccount)?;
+            let relayer_transfer_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.pool_token_account.to_account_info(),
+                    mint: ctx.accounts.token_mint.to_account_info(),
+                    to: relayer_token_account.to_account_info(),
+                    authority: ctx.accounts.pool.to_account_info(),
+                },
+                signer,
+            );
+            token_interface::transfer_checked(relayer_transfer_ctx, public_inputs.fee, decimals)?;
         }
         
-        // Mark the nullifier as used (simplified)
-        // mark_nullifier_used(nullifier);
+        // The relayer fronts the refund in SOL so the recipient can pay for transactions
+        if public_inputs.refund > 0 {
+            let relayer_account = ctx.accounts.relayer_account
+                .as_ref()
+                .ok_or(MiyaError::MissingRelayerAccount)?;
+            let refund_ctx = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: relayer_account.to_account_info(),
+                    to: ctx.accounts.recipient.to_account_info(),
+                },
+            );
+            system_program::transfer(refund_ctx, public_inputs.refund)?;
+        }
+        
+        // Update pool stats
+        let pool = &mut ctx.accounts.pool;
+        pool.record_withdrawal()?;
+        ctx.accounts.pool_token_account.reload()?;
+        check_vault_invariant(pool, ctx.accounts.pool_token_account.amount)?;
+        
+        // Emit withdrawal event
+        emit!(WithdrawEvent {
+            pool: pool.key(),
+            nullifier: public_inputs.nullifier,
+            recipient: public_inputs.recipient,
+            anonymity_set: pool.anonymity_set,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Withdrawal successful");
+        
+        Ok(())
+    }
+
+    pub fn deposit_native(ctx: Context<DepositNative>, commitment: [u8; 32], nullifier_hash: [u8; 32]) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        
+        // Ensure the pool is active
+        require!(pool.is_active, MiyaError::PoolInactive);
+        
+        let deposit_note = DepositNote {
+            commitment,
+            nullifier_hash,
+            timestamp: Clock::get()?.unix_timestamp,
+        };
+        
+        // Transfer lamports from the user to the pool vault
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.signer.to_account_info(),
+                to: ctx.accounts.vault.to_account_info(),
+            },
+        );
+        system_program::transfer(transfer_ctx, pool.deposit_amount)?;
+        
+        // Update pool stats
+        let leaf_index = pool.record_deposit()?;
+        check_vault_invariant(pool, vault_spendable_lamports(&ctx.accounts.vault.to_account_info())?)?;
+        
+        emit!(DepositEvent {
+            pool: pool.key(),
+            commitment,
+            leaf_index,
+            anonymity_set: pool.anonymity_set,
+            timestamp: deposit_note.timestamp,
+        });
+        
+        msg!("Deposit successful, commitment added to pool");
+        
+        Ok(())
+    }
+
+    pub fn withdraw_native(
+        ctx: Context<WithdrawNative>,
+        proof: Vec<u8>,
+        public_inputs: WithdrawPublicInputs,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        
+        // Ensure the pool is active
+        require!(pool.is_active, MiyaError::PoolInactive);
+        
+        // The recipient already receives SOL, so there is nothing to refund
+        require!(public_inputs.refund == 0, MiyaError::RefundNotSupported);
+        public_inputs.validate_relayer()?;
+        
+        let verified = verify_with_zkengine(
+            &ctx.accounts.verifier.to_account_info(),
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.zkengine_program.to_account_info(),
+            proof,
+            public_inputs.to_bytes(),
+        )?;
+        require!(verified, MiyaError::InvalidProof);
+        
+        // Creating the nullifier PDA fails if the note was already spent
+        let pool_nullifier = &mut ctx.accounts.pool_nullifier;
+        pool_nullifier.pool = pool.key();
+        pool_nullifier.nullifier = public_inputs.nullifier;
+        
+        check_relayer_fee(pool, public_inputs.relayer, public_inputs.fee, ctx.accounts.relayer_registration.as_ref())?;
+        
+        // Calculate the withdrawal amount
+        let withdraw_amount = pool.deposit_amount
+            .checked_sub(public_inputs.fee)
+            .ok_or(MiyaError::ArithmeticError)?;
+        
+        // The vault is owned by this program, so lamports are debited directly
+        let vault_info = ctx.accounts.vault.to_account_info();
+        let recipient_info = ctx.accounts.recipient.to_account_info();
+        move_lamports(&vault_info, &recipient_info, withdraw_amount)?;
+        
+        // Pay the proven fee to the relayer
+        if public_inputs.fee > 0 {
+            let relayer_info = ctx.accounts.relayer_account
+                .as_ref()
+                .ok_or(MiyaError::MissingRelayerAccount)?
+                .to_account_info();
+            move_lamports(&vault_info, &relayer_info, public_inputs.fee)?;
+        }
+        
+        // Update pool stats
+        pool.record_withdrawal()?;
+        check_vault_invariant(pool, vault_spendable_lamports(&ctx.accounts.vault.to_account_info())?)?;
         
         // Emit withdrawal event
         emit!(WithdrawEvent {
             pool: pool.key(),
-            nullifier,
-            recipient,
+            nullifier: public_inputs.nullifier,
+            recipient: public_inputs.recipient,
+            anonymity_set: pool.anonymity_set,
             timestamp: Clock::get()?.unix_timestamp,
         });
         
@@ -160,6 +504,327 @@ pub mod miya_mixer {
         Ok(())
     }
 
+    pub fn get_pool_stats(ctx: Context<GetPoolStats>) -> Result<PoolStats> {
+        let pool = &ctx.accounts.pool;
+        
+        Ok(PoolStats {
+            total_deposits: pool.total_deposits,
+            total_withdrawals: pool.total_withdrawals,
+            next_leaf_index: pool.next_leaf_index,
+            anonymity_set: pool.anonymity_set,
+        })
+    }
+
+    pub fn initialize_shielded_pool(ctx: Context<InitializeShieldedPool>, verifier: Pubkey) -> Result<()> {
+        validate_mint_extensions(&ctx.accounts.token_mint)?;
+        
+        let pool = &mut ctx.accounts.shielded_pool;
+        pool.authority = ctx.accounts.authority.key();
+        pool.token_mint = ctx.accounts.token_mint.key();
+        pool.verifier = verifier;
+        pool.next_leaf_index = 0;
+        pool.filled_subtrees = [[0; 32]; ShieldedPool::DEPTH];
+        pool.roots = [[0; 32]; ShieldedPool::ROOT_HISTORY_SIZE];
+        pool.roots[0] = ShieldedPool::empty_root();
+        pool.current_root_index = 0;
+        pool.total_value = 0;
+        pool.is_active = true;
+        
+        let (_, bump) = Pubkey::find_program_address(
+            &[b"miya_shielded_pool".as_ref(), pool.token_mint.as_ref()],
+            ctx.program_id,
+        );
+        pool.bump = bump;
+        
+        msg!("Shielded pool initialized for token mint: {}", pool.token_mint);
+        
+        Ok(())
+    }
+
+    pub fn transact(
+        ctx: Context<Transact>,
+        proof: Vec<u8>,
+        public_inputs: JoinSplitPublicInputs,
+    ) -> Result<()> {
+        let pool = &ctx.accounts.shielded_pool;
+        
+        require!(pool.is_active, MiyaError::PoolInactive);
+        require!(pool.is_known_root(&public_inputs.root), MiyaError::UnknownMerkleRoot);
+        require!(
+            (public_inputs.fee > 0) == public_inputs.relayer.is_some(),
+            MiyaError::RelayerFeeMismatch
+        );
+        
+        // The circuit proves the two spent notes plus the public amount equal the two new notes
+        let verified = verify_with_zkengine(
+            &ctx.accounts.verifier.to_account_info(),
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.zkengine_program.to_account_info(),
+            proof,
+            public_inputs.to_bytes()?,
+        )?;
+        require!(verified, MiyaError::InvalidProof);
+        
+        let seeds = &[
+            b"miya_shielded_pool".as_ref(),
+            pool.token_mint.as_ref(),
+            &[pool.bump],
+        ];
+        let signer = &[&seeds[..]];
+        let decimals = ctx.accounts.token_mint.decimals;
+        
+        // The notes change by the public amount: deposits add ext_amount and withdrawals
+        // remove |ext_amount|, with the relayer fee taken out of the notes in both cases
+        let total_value = public_inputs.settled_total_value(pool.total_value)?;
+        if public_inputs.ext_amount > 0 {
+            // Deposit: the payer funds the public amount from their own token account
+            let deposit_amount = public_inputs.ext_amount as u64;
+            let depositor_token_account = ctx.accounts.depositor_token_account
+                .as_ref()
+                .ok_or(MiyaError::MissingTokenAccount)?;
+            let vault_balance_before = ctx.accounts.vault.amount;
+            let transfer_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: depositor_token_account.to_account_info(),
+                    mint: ctx.accounts.token_mint.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                    authority: ctx.accounts.payer.to_account_info(),
+                },
+            );
+            token_interface::transfer_checked(transfer_ctx, deposit_amount, decimals)?;
+            
+            ctx.accounts.vault.reload()?;
+            let received = ctx.accounts.vault.amount
+                .checked_sub(vault_balance_before)
+                .ok_or(MiyaError::ArithmeticError)?;
+            require!(received >= deposit_amount, MiyaError::DepositAmountMismatch);
+        } else if public_inputs.ext_amount < 0 {
+            // Withdrawal: the recipient receives the full external amount
+            let withdraw_amount = public_inputs.ext_amount.unsigned_abs();
+            let recipient_token_account = ctx.accounts.recipient_token_account
+                .as_ref()
+                .ok_or(MiyaError::MissingTokenAccount)?;
+            let transfer_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.vault.to_account_info(),
+                    mint: ctx.accounts.token_mint.to_account_info(),
+                    to: recipient_token_account.to_account_info(),
+                    authority: ctx.accounts.shielded_pool.to_account_info(),
+                },
+                signer,
+            );
+            token_interface::transfer_checked(transfer_ctx, withdraw_amount, decimals)?;
+        }
+        
+        // The fee is paid on top of the external amount, out of the shielded balance
+        if public_inputs.fee > 0 {
+            let relayer_token_account = ctx.accounts.relayer_token_account
+                .as_ref()
+                .ok_or(MiyaError::MissingRelayerAccount)?;
+            let fee_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.vault.to_account_info(),
+                    mint: ctx.accounts.token_mint.to_account_info(),
+                    to: relayer_token_account.to_account_info(),
+                    authority: ctx.accounts.shielded_pool.to_account_info(),
+                },
+                signer,
+            );
+            token_interface::transfer_checked(fee_ctx, public_inputs.fee, decimals)?;
+        }
+        
+        // Both nullifier PDAs were created by this instruction, so neither input was spent before
+        let pool_key = ctx.accounts.shielded_pool.key();
+        ctx.accounts.input_nullifier_0.pool = pool_key;
+        ctx.accounts.input_nullifier_0.nullifier = public_inputs.input_nullifiers[0];
+        ctx.accounts.input_nullifier_1.pool = pool_key;
+        ctx.accounts.input_nullifier_1.nullifier = public_inputs.input_nullifiers[1];
+        
+        let pool = &mut ctx.accounts.shielded_pool;
+        let mut leaf_indices = [0u64; 2];
+        for (leaf_index, commitment) in leaf_indices.iter_mut().zip(public_inputs.output_commitments.iter()) {
+            *leaf_index = pool.insert(*commitment)?;
+        }
+        pool.total_value = total_value;
+        
+        ctx.accounts.vault.reload()?;
+        require!(
+            ctx.accounts.vault.amount >= ctx.accounts.shielded_pool.total_value,
+            MiyaError::VaultInvariantViolated
+        );
+        
+        emit!(ShieldedTransactionEvent {
+            pool: pool_key,
+            input_nullifiers: public_inputs.input_nullifiers,
+            output_commitments: public_inputs.output_commitments,
+            leaf_indices,
+            ext_amount: public_inputs.ext_amount,
+            root: ctx.accounts.shielded_pool.current_root(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Shielded transaction processed");
+        
+        Ok(())
+    }
+
+    pub fn set_relayer_policy(
+        ctx: Context<UpdatePool>,
+        max_relayer_fee_bps: u16,
+        require_registered_relayer: bool,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        
+        // Only the pool authority can change the relayer policy
+        require!(
+            ctx.accounts.authority.key() == pool.authority,
+            MiyaError::Unauthorized
+        );
+        require!(max_relayer_fee_bps <= MAX_FEE_BPS, MiyaError::InvalidFeeBps);
+        
+        pool.max_relayer_fee_bps = max_relayer_fee_bps;
+        pool.require_registered_relayer = require_registered_relayer;
+        
+        msg!("Relayer fee cap set to {} bps", max_relayer_fee_bps);
+        
+        Ok(())
+    }
+
+    pub fn register_relayer(ctx: Context<RegisterRelayer>, stake: u64, fee_bps: u16) -> Result<()> {
+        require!(stake >= Relayer::MIN_STAKE, MiyaError::InsufficientRelayerStake);
+        require!(fee_bps <= MAX_FEE_BPS, MiyaError::InvalidFeeBps);
+        
+        // The stake is bonded as lamports on the relayer account itself
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.owner.to_account_info(),
+                to: ctx.accounts.relayer.to_account_info(),
+            },
+        );
+        system_program::transfer(transfer_ctx, stake)?;
+        
+        let relayer = &mut ctx.accounts.relayer;
+        relayer.owner = ctx.accounts.owner.key();
+        relayer.stake = stake;
+        relayer.fee_bps = fee_bps;
+        relayer.is_active = true;
+        relayer.deactivated_at = 0;
+        
+        let (_, bump) = Pubkey::find_program_address(
+            &[b"miya_relayer".as_ref(), relayer.owner.as_ref()],
+            ctx.program_id,
+        );
+        relayer.bump = bump;
+        
+        emit!(RelayerRegisteredEvent {
+            relayer: relayer.owner,
+            stake,
+            fee_bps,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Relayer registered with stake: {}", stake);
+        
+        Ok(())
+    }
+
+    pub fn update_relayer(
+        ctx: Context<UpdateRelayer>,
+        fee_bps: Option<u16>,
+        is_active: Option<bool>,
+    ) -> Result<()> {
+        let relayer = &mut ctx.accounts.relayer;
+        
+        if let Some(fee_bps) = fee_bps {
+            require!(fee_bps <= MAX_FEE_BPS, MiyaError::InvalidFeeBps);
+            relayer.fee_bps = fee_bps;
+        }
+        
+        if let Some(is_active) = is_active {
+            // Deactivating starts the unbonding period for the stake
+            if relayer.is_active && !is_active {
+                relayer.deactivated_at = Clock::get()?.unix_timestamp;
+            }
+            // A slashed stake has to be restored by registering again
+            if is_active {
+                require!(relayer.stake >= Relayer::MIN_STAKE, MiyaError::InsufficientRelayerStake);
+            }
+            relayer.is_active = is_active;
+        }
+        
+        emit!(RelayerUpdatedEvent {
+            relayer: relayer.owner,
+            fee_bps: relayer.fee_bps,
+            is_active: relayer.is_active,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    pub fn deregister_relayer(ctx: Context<DeregisterRelayer>) -> Result<()> {
+        let relayer = &ctx.accounts.relayer;
+        require!(!relayer.is_active, MiyaError::RelayerStillActive);
+        
+        let unbonded_at = relayer.deactivated_at
+            .checked_add(Relayer::UNBONDING_PERIOD)
+            .ok_or(MiyaError::ArithmeticError)?;
+        require!(
+            Clock::get()?.unix_timestamp >= unbonded_at,
+            MiyaError::UnbondingPeriodNotElapsed
+        );
+        
+        // Closing the account returns the stake and rent to the owner
+        emit!(RelayerDeregisteredEvent {
+            relayer: relayer.owner,
+            stake: relayer.stake,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    pub fn slash_relayer(ctx: Context<SlashRelayer>, amount: u64) -> Result<()> {
+        // Only the mixer authority can slash, and only out of the bonded stake
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.config.authority,
+            MiyaError::Unauthorized
+        );
+        require!(amount > 0, MiyaError::InvalidSlashAmount);
+        
+        let relayer = &mut ctx.accounts.relayer;
+        relayer.stake = relayer.stake
+            .checked_sub(amount)
+            .ok_or(MiyaError::InsufficientRelayerStake)?;
+        
+        // A slashed relayer stops relaying and restarts its unbonding period
+        let current_time = Clock::get()?.unix_timestamp;
+        relayer.is_active = false;
+        relayer.deactivated_at = current_time;
+        
+        move_lamports(
+            &relayer.to_account_info(),
+            &ctx.accounts.recipient.to_account_info(),
+            amount,
+        )?;
+        
+        emit!(RelayerSlashedEvent {
+            relayer: relayer.owner,
+            amount,
+            remaining_stake: relayer.stake,
+            recipient: ctx.accounts.recipient.key(),
+            timestamp: current_time,
+        });
+        
+        msg!("Relayer slashed by {} lamports", amount);
+        
+        Ok(())
+    }
+
     pub fn pause_pool(ctx: Context<UpdatePool>) -> Result<()> {
         let pool = &mut ctx.accounts.pool;
         require!(pool.is_active, MiyaError::PoolAlreadyPaused);
@@ -191,49 +856,752 @@ pub mod miya_mixer {
         
         Ok(())
     }
-}
 
-// Function to verify the zero-knowledge proof (placeholder implementation)
-fn verify_proof(_proof: &[u8], _nullifier: &[u8; 32], _recipient: &Pubkey) -> bool {
-    // This would call into the zkengine module or verify the proof on-chain
-    // For now, return true for development purposes
-    true
-}
-
-// Function to check if a nullifier has been used (placeholder implementation)
-fn is_nullifier_used(_nullifier: [u8; 32]) -> bool {
-    // This would check against a stored list of used nullifiers
-    // For now, return false for development purposes
-    false
-}
-
-#[derive(Accounts)]
-pub struct InitializePool<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + Pool::LEN,
-        seeds = [
-            b"miya_pool",
-            token_mint.key().as_ref(),
-            &deposit_amount.to_le_bytes(),
-        ],
-        bump
+    pub fn transfer_pool_authority(
+        ctx: Context<UpdatePool>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        
+        // Only the current pool authority can transfer authority
+        require!(
+            ctx.accounts.authority.key() == pool.authority,
+            MiyaError::Unauthorized
+        );
+        
+        // The transfer only completes once the new authority accepts it
+        pool.pending_authority = Some(new_authority);
+        
+        emit!(AuthorityTransferProposedEvent {
+            pool: pool.key(),
+            authority: pool.authority,
+            pending_authority: new_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Pool authority transfer proposed to: {}", new_authority);
+        
+        Ok(())
+    }
+
+    pub fn accept_pool_authority(ctx: Context<AcceptPoolAuthority>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        let new_authority = ctx.accounts.new_authority.key();
+        
+        // Only the pending authority can accept the transfer
+        let pending_authority = pool.pending_authority.ok_or(MiyaError::NoPendingAuthority)?;
+        require!(
+            new_authority == pending_authority,
+            MiyaError::Unauthorized
+        );
+        
+        // Update the authority
+        let old_authority = pool.authority;
+        pool.authority = new_authority;
+        pool.pending_authority = None;
+        
+        emit!(AuthorityTransferredEvent {
+            pool: pool.key(),
+            old_authority,
+            new_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Pool authority transferred to: {}", new_authority);
+        
+        Ok(())
+    }
+
+    pub fn cancel_pool_authority_transfer(ctx: Context<UpdatePool>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        
+        // Only the current pool authority can cancel a pending transfer
+        require!(
+            ctx.accounts.authority.key() == pool.authority,
+            MiyaError::Unauthorized
+        );
+        
+        let pending_authority = pool.pending_authority.take().ok_or(MiyaError::NoPendingAuthority)?;
+        
+        emit!(AuthorityTransferCanceledEvent {
+            pool: pool.key(),
+            authority: pool.authority,
+            pending_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Pool authority transfer canceled");
+        
+        Ok(())
+    }
+}
+
+// Instruction data for an Anchor instruction: its 8-byte sighash, then the borsh arguments
+fn anchor_instruction_data(name: &str, args: &[u8]) -> Vec<u8> {
+    let mut data = hash(format!("global:{}", name).as_bytes()).to_bytes()[..8].to_vec();
+    data.extend_from_slice(args);
+    data
+}
+
+// Check a proof with the zk engine's verify_proof and read back its boolean result.
+// The verifier account is read by hand rather than through the zk engine crate:
+// discriminator, key, then the verifier type.
+fn verify_with_zkengine<'info>(
+    verifier: &AccountInfo<'info>,
+    signer: &AccountInfo<'info>,
+    zkengine_program: &AccountInfo<'info>,
+    proof: Vec<u8>,
+    public_inputs: Vec<u8>,
+) -> Result<bool> {
+    require_keys_eq!(*verifier.owner, ZKENGINE_PROGRAM_ID, MiyaError::InvalidVerifier);
+    {
+        let data = verifier.try_borrow_data()?;
+        let discriminator = hash(b"account:Verifier").to_bytes();
+        require!(
+            data.len() > 8 + 32 && data[..8] == discriminator[..8],
+            MiyaError::InvalidVerifier
+        );
+        require!(data[8 + 32] == GROTH16_VERIFIER_TYPE, MiyaError::UnsupportedVerifier);
+    }
+    
+    let instruction = Instruction {
+        program_id: ZKENGINE_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(verifier.key(), false),
+            AccountMeta::new_readonly(signer.key(), true),
+        ],
+        data: anchor_instruction_data("verify_proof", &(proof, public_inputs).try_to_vec()?),
+    };
+    invoke(&instruction, &[verifier.clone(), signer.clone(), zkengine_program.clone()])?;
+    
+    let (program_id, data) = get_return_data().ok_or(MiyaError::InvalidProof)?;
+    require_keys_eq!(program_id, ZKENGINE_PROGRAM_ID, MiyaError::InvalidProof);
+    
+    Ok(bool::try_from_slice(&data)?)
+}
+
+// Hash of the transaction data the proof does not otherwise see, so a relayer
+// cannot redirect the withdrawal or change its fee
+fn ext_data_hash(recipient: &Pubkey, relayer: &Option<Pubkey>, ext_amount: i64, fee: u64) -> [u8; 32] {
+    keccak::hashv(&[
+        recipient.as_ref(),
+        relayer.unwrap_or_default().as_ref(),
+        &ext_amount.to_le_bytes(),
+        &fee.to_le_bytes(),
+    ])
+    .0
+}
+
+// Cap the fee at the pool limit and, when a relayer takes it, at the relayer's
+// advertised rate; pools may also insist that the relayer is registered
+fn check_relayer_fee(
+    pool: &Pool,
+    relayer: Option<Pubkey>,
+    fee_amount: u64,
+    registration: Option<&Account<Relayer>>,
+) -> Result<()> {
+    require!(
+        fee_amount <= bps_of(pool.deposit_amount, pool.max_relayer_fee_bps)?,
+        MiyaError::RelayerFeeTooHigh
+    );
+    
+    let relayer_key = match relayer {
+        Some(relayer_key) => relayer_key,
+        None => return Ok(()),
+    };
+    
+    match registration {
+        Some(registration) => {
+            require!(registration.owner == relayer_key, MiyaError::RelayerNotRegistered);
+            require!(registration.is_active, MiyaError::RelayerInactive);
+            require!(
+                fee_amount <= bps_of(pool.deposit_amount, registration.fee_bps)?,
+                MiyaError::RelayerFeeTooHigh
+            );
+        }
+        None => require!(!pool.require_registered_relayer, MiyaError::RelayerNotRegistered),
+    }
+    
+    Ok(())
+}
+
+fn bps_of(amount: u64, bps: u16) -> Result<u64> {
+    let value = (amount as u128)
+        .checked_mul(bps as u128)
+        .ok_or(MiyaError::ArithmeticError)?
+        / MAX_FEE_BPS as u128;
+    Ok(value as u64)
+}
+
+// Only accept Token-2022 mints whose extensions can't change the amount received,
+// move or freeze pooled balances, hide them, or let the mint be closed and re-created
+// under the pool's address; anything newer is refused by default
+fn validate_mint_extensions(mint: &InterfaceAccount<Mint>) -> Result<()> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != spl_token_2022::ID {
+        return Ok(());
+    }
+    
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+    for extension in mint_state.get_extension_types()? {
+        require!(
+            matches!(
+                extension,
+                ExtensionType::InterestBearingConfig
+                    | ExtensionType::MetadataPointer
+                    | ExtensionType::TokenMetadata
+            ),
+            MiyaError::UnsupportedMintExtension
+        );
+    }
+    
+    Ok(())
+}
+
+// The vault must always cover every unspent note at the pool's denomination
+fn check_vault_invariant(pool: &Pool, vault_balance: u64) -> Result<()> {
+    let outstanding_notes = pool.total_deposits
+        .checked_sub(pool.total_withdrawals)
+        .ok_or(MiyaError::ArithmeticError)?;
+    let required_balance = outstanding_notes
+        .checked_mul(pool.deposit_amount)
+        .ok_or(MiyaError::ArithmeticError)?;
+    require!(vault_balance >= required_balance, MiyaError::VaultInvariantViolated);
+    
+    Ok(())
+}
+
+// Lamports held by a native vault on top of its rent-exempt minimum
+fn vault_spendable_lamports(vault: &AccountInfo) -> Result<u64> {
+    let rent_exempt_minimum = Rent::get()?.minimum_balance(vault.data_len());
+    Ok(vault.lamports().saturating_sub(rent_exempt_minimum))
+}
+
+// Create a program-owned PDA the way Anchor's init does: lamports sent to the
+// address ahead of time must not make create_account fail, so top up, allocate
+// and assign instead when the account already holds lamports
+fn create_pda_account<'info>(
+    system_program: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    account: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    space: usize,
+    owner: &Pubkey,
+) -> Result<()> {
+    let rent_exempt = Rent::get()?.minimum_balance(space);
+    
+    if account.lamports() == 0 {
+        return system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                system_program::CreateAccount {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+                signer_seeds,
+            ),
+            rent_exempt,
+            space as u64,
+            owner,
+        );
+    }
+    
+    let shortfall = rent_exempt.saturating_sub(account.lamports());
+    if shortfall > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+    system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            system_program::Allocate {
+                account_to_allocate: account.clone(),
+            },
+            signer_seeds,
+        ),
+        space as u64,
+    )?;
+    system_program::assign(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            system_program::Assign {
+                account_to_assign: account.clone(),
+            },
+            signer_seeds,
+        ),
+        owner,
+    )
+}
+
+// Move lamports out of a program-owned account
+fn move_lamports(from: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<()> {
+    **from.try_borrow_mut_lamports()? = from
+        .lamports()
+        .checked_sub(amount)
+        .ok_or(MiyaError::InsufficientVaultBalance)?;
+    **to.try_borrow_mut_lamports()? = to
+        .lamports()
+        .checked_add(amount)
+        .ok_or(MiyaError::ArithmeticError)?;
+    
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeMixerConfig<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + MixerConfig::LEN,
+        seeds = [b"miya_mixer_config"],
+        bump
+    )]
+    pub config: Account<'info, MixerConfig>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateDenominationPools<'info> {
+    #[account(
+        seeds = [b"miya_mixer_config"],
+        bump = config.bump,
+        has_one = authority @ MiyaError::Unauthorized,
+    )]
+    pub config: Account<'info, MixerConfig>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + DenominationRegistry::LEN,
+        seeds = [b"miya_denominations", token_mint.key().as_ref()],
+        bump
+    )]
+    pub registry: Account<'info, DenominationRegistry>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct GetDenominationPools<'info> {
+    #[account(
+        seeds = [b"miya_denominations", registry.token_mint.as_ref()],
+        bump = registry.bump,
+    )]
+    pub registry: Account<'info, DenominationRegistry>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_mint: Pubkey, deposit_amount: u64)]
+pub struct InitializePool<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Pool::LEN,
+        seeds = [
+            b"miya_pool",
+            token_mint.key().as_ref(),
+            &deposit_amount.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(seeds = [b"miya_mixer_config"], bump = config.bump)]
+    pub config: Account<'info, MixerConfig>,
+    
+    /// Pools may only be created with the mixer authority's approval
+    #[account(address = config.authority @ MiyaError::Unauthorized)]
+    pub config_authority: Signer<'info>,
+    
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(deposit_amount: u64)]
+pub struct InitializeNativePool<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Pool::LEN,
+        seeds = [
+            b"miya_pool",
+            Pool::NATIVE_MINT.as_ref(),
+            &deposit_amount.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + SolVault::LEN,
+        seeds = [b"miya_sol_vault", pool.key().as_ref()],
+        bump
+    )]
+    pub vault: Account<'info, SolVault>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(seeds = [b"miya_mixer_config"], bump = config.bump)]
+    pub config: Account<'info, MixerConfig>,
+    
+    #[account(address = config.authority @ MiyaError::Unauthorized)]
+    pub config_authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"miya_pool",
+            pool.token_mint.as_ref(),
+            &pool.deposit_amount.to_le_bytes(),
+        ],
+        bump = pool.bump,
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = user_token_account.owner == signer.key(),
+        constraint = user_token_account.mint == pool.token_mint,
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    /// The pool's associated token account is its only vault
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program,
+    )]
+    pub pool_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = pool.token_mint)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(proof: Vec<u8>, public_inputs: WithdrawPublicInputs)]
+pub struct Withdraw<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"miya_pool",
+            pool.token_mint.as_ref(),
+            &pool.deposit_amount.to_le_bytes(),
+        ],
+        bump = pool.bump,
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PoolNullifier::LEN,
+        seeds = [b"miya_nullifier", pool.key().as_ref(), public_inputs.nullifier.as_ref()],
+        bump
+    )]
+    pub pool_nullifier: Account<'info, PoolNullifier>,
+    
+    /// The pool's associated token account is its only vault
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program,
+    )]
+    pub pool_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = recipient_token_account.mint == pool.token_mint,
+        constraint = recipient_token_account.owner == public_inputs.recipient @ MiyaError::InvalidRecipientAccount,
+    )]
+    pub recipient_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    /// Receives the SOL refund, if any
+    #[account(mut, address = public_inputs.recipient)]
+    pub recipient: SystemAccount<'info>,
+    
+    /// Optional relayer token account for fees
+    #[account(
+        mut,
+        constraint = relayer_token_account.mint == pool.token_mint,
+        constraint = Some(relayer_token_account.owner) == public_inputs.relayer @ MiyaError::InvalidRelayerAccount,
+    )]
+    pub relayer_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Relayer paying the refund
+    #[account(
+        mut,
+        constraint = Some(relayer_account.key()) == public_inputs.relayer @ MiyaError::InvalidRelayerAccount,
+    )]
+    pub relayer_account: Option<Signer<'info>>,
+    
+    /// Registry entry of the relayer, required when the pool only accepts registered relayers
+    #[account(
+        seeds = [b"miya_relayer", relayer_registration.owner.as_ref()],
+        bump = relayer_registration.bump,
+    )]
+    pub relayer_registration: Option<Account<'info, Relayer>>,
+    
+    #[account(address = pool.token_mint)]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    
+    /// CHECK: the pool's zk engine verifier; its layout is checked before the proof is
+    #[account(mut, address = pool.verifier @ MiyaError::InvalidVerifier)]
+    pub verifier: UncheckedAccount<'info>,
+    
+    /// CHECK: the zk engine program
+    #[account(address = ZKENGINE_PROGRAM_ID)]
+    pub zkengine_program: UncheckedAccount<'info>,
+    
+    /// Pays for the nullifier; a relayer can submit the withdrawal
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositNative<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"miya_pool",
+            pool.token_mint.as_ref(),
+            &pool.deposit_amount.to_le_bytes(),
+        ],
+        bump = pool.bump,
+        constraint = pool.token_mint == Pool::NATIVE_MINT @ MiyaError::NotNativePool,
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    #[account(
+        mut,
+        seeds = [b"miya_sol_vault", pool.key().as_ref()],
+        bump = vault.bump,
+    )]
+    pub vault: Account<'info, SolVault>,
+    
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(proof: Vec<u8>, public_inputs: WithdrawPublicInputs)]
+pub struct WithdrawNative<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"miya_pool",
+            pool.token_mint.as_ref(),
+            &pool.deposit_amount.to_le_bytes(),
+        ],
+        bump = pool.bump,
+        constraint = pool.token_mint == Pool::NATIVE_MINT @ MiyaError::NotNativePool,
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    #[account(
+        mut,
+        seeds = [b"miya_sol_vault", pool.key().as_ref()],
+        bump = vault.bump,
+    )]
+    pub vault: Account<'info, SolVault>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PoolNullifier::LEN,
+        seeds = [b"miya_nullifier", pool.key().as_ref(), public_inputs.nullifier.as_ref()],
+        bump
+    )]
+    pub pool_nullifier: Account<'info, PoolNullifier>,
+    
+    #[account(mut, address = public_inputs.recipient)]
+    pub recipient: SystemAccount<'info>,
+    
+    /// Optional relayer receiving the fee
+    #[account(
+        mut,
+        constraint = Some(relayer_account.key()) == public_inputs.relayer @ MiyaError::InvalidRelayerAccount,
+    )]
+    pub relayer_account: Option<SystemAccount<'info>>,
+    
+    /// Registry entry of the relayer, required when the pool only accepts registered relayers
+    #[account(
+        seeds = [b"miya_relayer", relayer_registration.owner.as_ref()],
+        bump = relayer_registration.bump,
+    )]
+    pub relayer_registration: Option<Account<'info, Relayer>>,
+    
+    /// CHECK: the pool's zk engine verifier; its layout is checked before the proof is
+    #[account(mut, address = pool.verifier @ MiyaError::InvalidVerifier)]
+    pub verifier: UncheckedAccount<'info>,
+    
+    /// CHECK: the zk engine program
+    #[account(address = ZKENGINE_PROGRAM_ID)]
+    pub zkengine_program: UncheckedAccount<'info>,
+    
+    /// Pays for the nullifier; a relayer can submit the withdrawal
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeShieldedPool<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ShieldedPool::LEN,
+        seeds = [b"miya_shielded_pool", token_mint.key().as_ref()],
+        bump
+    )]
+    pub shielded_pool: Box<Account<'info, ShieldedPool>>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(seeds = [b"miya_mixer_config"], bump = config.bump)]
+    pub config: Account<'info, MixerConfig>,
+    
+    #[account(address = config.authority @ MiyaError::Unauthorized)]
+    pub config_authority: Signer<'info>,
+    
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(proof: Vec<u8>, public_inputs: JoinSplitPublicInputs)]
+pub struct Transact<'info> {
+    #[account(
+        mut,
+        seeds = [b"miya_shielded_pool", shielded_pool.token_mint.as_ref()],
+        bump = shielded_pool.bump,
+        has_one = verifier,
+    )]
+    pub shielded_pool: Box<Account<'info, ShieldedPool>>,
+    
+    #[account(
+        mut,
+        constraint = vault.owner == shielded_pool.key(),
+        constraint = vault.mint == shielded_pool.token_mint,
+    )]
+    pub vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ShieldedNullifier::LEN,
+        seeds = [
+            b"miya_shielded_nullifier",
+            shielded_pool.key().as_ref(),
+            public_inputs.input_nullifiers[0].as_ref(),
+        ],
+        bump
     )]
-    pub pool: Account<'info, Pool>,
+    pub input_nullifier_0: Account<'info, ShieldedNullifier>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ShieldedNullifier::LEN,
+        seeds = [
+            b"miya_shielded_nullifier",
+            shielded_pool.key().as_ref(),
+            public_inputs.input_nullifiers[1].as_ref(),
+        ],
+        bump
+    )]
+    pub input_nullifier_1: Account<'info, ShieldedNullifier>,
+    
+    /// Source of the public amount on deposits
+    #[account(
+        mut,
+        constraint = depositor_token_account.owner == payer.key(),
+        constraint = depositor_token_account.mint == shielded_pool.token_mint,
+    )]
+    pub depositor_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    
+    /// Destination of the public amount on withdrawals
+    #[account(
+        mut,
+        constraint = recipient_token_account.owner == public_inputs.recipient @ MiyaError::InvalidRecipientAccount,
+        constraint = recipient_token_account.mint == shielded_pool.token_mint,
+    )]
+    pub recipient_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    
+    #[account(
+        mut,
+        constraint = Some(relayer_token_account.owner) == public_inputs.relayer @ MiyaError::InvalidRelayerAccount,
+        constraint = relayer_token_account.mint == shielded_pool.token_mint,
+    )]
+    pub relayer_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
     
+    #[account(address = shielded_pool.token_mint)]
+    pub token_mint: Box<InterfaceAccount<'info, Mint>>,
+    
+    /// CHECK: the pool's zk engine verifier; its layout is checked before the proof is
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub verifier: UncheckedAccount<'info>,
     
-    pub token_mint: Account<'info, token::Mint>,
+    /// Pays for the nullifiers and funds deposits; a relayer can submit withdrawals
+    #[account(mut)]
+    pub payer: Signer<'info>,
     
+    /// CHECK: the zk engine program
+    #[account(address = ZKENGINE_PROGRAM_ID)]
+    pub zkengine_program: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct Deposit<'info> {
+pub struct GetPoolStats<'info> {
     #[account(
-        mut,
         seeds = [
             b"miya_pool",
             pool.token_mint.as_ref(),
@@ -242,29 +1610,10 @@ pub struct Deposit<'info> {
         bump = pool.bump,
     )]
     pub pool: Account<'info, Pool>,
-    
-    #[account(mut)]
-    pub signer: Signer<'info>,
-    
-    #[account(
-        mut,
-        constraint = user_token_account.owner == signer.key(),
-        constraint = user_token_account.mint == pool.token_mint,
-    )]
-    pub user_token_account: Account<'info, TokenAccount>,
-    
-    #[account(
-        mut,
-        constraint = pool_token_account.owner == pool.key(),
-        constraint = pool_token_account.mint == pool.token_mint,
-    )]
-    pub pool_token_account: Account<'info, TokenAccount>,
-    
-    pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
-pub struct Withdraw<'info> {
+pub struct UpdatePool<'info> {
     #[account(
         mut,
         seeds = [
@@ -276,31 +1625,75 @@ pub struct Withdraw<'info> {
     )]
     pub pool: Account<'info, Pool>,
     
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterRelayer<'info> {
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Relayer::LEN,
+        seeds = [b"miya_relayer", owner.key().as_ref()],
+        bump
+    )]
+    pub relayer: Account<'info, Relayer>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateRelayer<'info> {
     #[account(
         mut,
-        constraint = pool_token_account.owner == pool.key(),
-        constraint = pool_token_account.mint == pool.token_mint,
+        seeds = [b"miya_relayer", owner.key().as_ref()],
+        bump = relayer.bump,
+        has_one = owner @ MiyaError::Unauthorized,
     )]
-    pub pool_token_account: Account<'info, TokenAccount>,
+    pub relayer: Account<'info, Relayer>,
     
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DeregisterRelayer<'info> {
     #[account(
         mut,
-        constraint = recipient_token_account.mint == pool.token_mint,
+        seeds = [b"miya_relayer", owner.key().as_ref()],
+        bump = relayer.bump,
+        has_one = owner @ MiyaError::Unauthorized,
+        close = owner,
     )]
-    pub recipient_token_account: Account<'info, TokenAccount>,
+    pub relayer: Account<'info, Relayer>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SlashRelayer<'info> {
+    #[account(seeds = [b"miya_mixer_config"], bump = config.bump)]
+    pub config: Account<'info, MixerConfig>,
     
-    /// Optional relayer token account for fees
     #[account(
         mut,
-        constraint = relayer_token_account.mint == pool.token_mint,
+        seeds = [b"miya_relayer", relayer.owner.as_ref()],
+        bump = relayer.bump,
     )]
-    pub relayer_token_account: Option<Account<'info, TokenAccount>>,
+    pub relayer: Account<'info, Relayer>,
+    
+    /// Receives the slashed stake
+    #[account(mut)]
+    pub recipient: SystemAccount<'info>,
     
-    pub token_program: Program<'info, Token>,
+    pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct UpdatePool<'info> {
+pub struct AcceptPoolAuthority<'info> {
     #[account(
         mut,
         seeds = [
@@ -312,21 +1705,277 @@ pub struct UpdatePool<'info> {
     )]
     pub pool: Account<'info, Pool>,
     
-    pub authority: Signer<'info>,
+    pub new_authority: Signer<'info>,
 }
 
 #[account]
 pub struct Pool {
     pub authority: Pubkey,        // Authority allowed to admin the pool
+    pub pending_authority: Option<Pubkey>, // Proposed authority awaiting acceptance
     pub token_mint: Pubkey,       // The token mint address
+    pub verifier: Pubkey,         // zk engine verifier for withdrawal proofs
     pub deposit_amount: u64,      // Fixed amount for deposits
     pub total_deposits: u64,      // Number of deposits made
+    pub total_withdrawals: u64,   // Number of withdrawals made
+    pub next_leaf_index: u64,     // Tree index assigned to the next commitment
+    pub anonymity_set: u64,       // Unspent notes currently in the pool
+    pub max_relayer_fee_bps: u16, // Largest relayer fee accepted, in basis points of the denomination
+    pub require_registered_relayer: bool, // Whether relayers must be in the registry
     pub is_active: bool,          // Whether the pool is active or paused
     pub bump: u8,                 // Bump seed for PDA
 }
 
 impl Pool {
-    pub const LEN: usize = 32 + 32 + 8 + 8 + 1 + 1;
+    // Sentinel mint for pools that hold native SOL instead of an SPL token
+    pub const NATIVE_MINT: Pubkey = Pubkey::new_from_array([0; 32]);
+    
+    pub const DEFAULT_MAX_RELAYER_FEE_BPS: u16 = 500;
+    
+    pub const LEN: usize = 32 + 33 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 2 + 1 + 1 + 1;
+    
+    // Count a deposit and return the leaf index of its commitment
+    pub fn record_deposit(&mut self) -> Result<u64> {
+        let leaf_index = self.next_leaf_index;
+        self.next_leaf_index = leaf_index.checked_add(1).ok_or(MiyaError::ArithmeticError)?;
+        self.total_deposits = self.total_deposits.checked_add(1).ok_or(MiyaError::ArithmeticError)?;
+        self.anonymity_set = self.anonymity_set.checked_add(1).ok_or(MiyaError::ArithmeticError)?;
+        
+        Ok(leaf_index)
+    }
+    
+    pub fn record_withdrawal(&mut self) -> Result<()> {
+        self.total_withdrawals = self.total_withdrawals.checked_add(1).ok_or(MiyaError::ArithmeticError)?;
+        self.anonymity_set = self.anonymity_set.checked_sub(1).ok_or(MiyaError::ArithmeticError)?;
+        
+        Ok(())
+    }
+}
+
+#[account]
+pub struct MixerConfig {
+    pub authority: Pubkey,        // Authority allowed to create pools
+    pub bump: u8,                 // Bump seed for PDA
+}
+
+impl MixerConfig {
+    pub const LEN: usize = 32 + 1;
+}
+
+#[account]
+pub struct DenominationRegistry {
+    pub token_mint: Pubkey,       // Mint the denominations are for
+    pub denominations: Vec<u64>,  // Approved deposit amounts, each backed by a pool
+    pub bump: u8,                 // Bump seed for PDA
+}
+
+impl DenominationRegistry {
+    pub const MAX_DENOMINATIONS: usize = 8;
+    
+    pub const LEN: usize = 32 + 4 + 8 * Self::MAX_DENOMINATIONS + 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct DenominationPool {
+    pub deposit_amount: u64,
+    pub pool: Pubkey,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct PoolStats {
+    pub total_deposits: u64,
+    pub total_withdrawals: u64,
+    pub next_leaf_index: u64,
+    pub anonymity_set: u64,
+}
+
+// Everything the withdrawal proof commits to, so none of it can be swapped by whoever submits the transaction
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct WithdrawPublicInputs {
+    pub nullifier: [u8; 32],
+    pub recipient: Pubkey,
+    pub relayer: Option<Pubkey>,
+    pub fee: u64,
+    pub refund: u64,
+}
+
+impl WithdrawPublicInputs {
+    // Serialized in circuit order, with an absent relayer encoded as the zero key
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(32 + 32 + 32 + 8 + 8);
+        bytes.extend_from_slice(&self.nullifier);
+        bytes.extend_from_slice(self.recipient.as_ref());
+        bytes.extend_from_slice(self.relayer.unwrap_or_default().as_ref());
+        bytes.extend_from_slice(&self.fee.to_le_bytes());
+        bytes.extend_from_slice(&self.refund.to_le_bytes());
+        bytes
+    }
+    
+    pub fn validate_relayer(&self) -> Result<()> {
+        let pays_relayer = self.fee > 0 || self.refund > 0;
+        require!(pays_relayer == self.relayer.is_some(), MiyaError::RelayerFeeMismatch);
+        
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct JoinSplitPublicInputs {
+    pub root: [u8; 32],
+    pub input_nullifiers: [[u8; 32]; 2],
+    pub output_commitments: [[u8; 32]; 2],
+    pub ext_amount: i64,          // Positive for deposits, negative for withdrawals
+    pub fee: u64,
+    pub recipient: Pubkey,
+    pub relayer: Option<Pubkey>,
+}
+
+impl JoinSplitPublicInputs {
+    // What enters the notes: the external amount net of the relayer fee
+    pub fn public_amount(&self) -> Result<i64> {
+        let fee = i64::try_from(self.fee).map_err(|_| MiyaError::ArithmeticError)?;
+        Ok(self.ext_amount.checked_sub(fee).ok_or(MiyaError::ArithmeticError)?)
+    }
+    
+    // Value owed to unspent notes once this transaction settles
+    pub fn settled_total_value(&self, total_value: u64) -> Result<u64> {
+        let public_amount = self.public_amount()?;
+        let settled = if public_amount >= 0 {
+            total_value.checked_add(public_amount as u64)
+        } else {
+            total_value.checked_sub(public_amount.unsigned_abs())
+        };
+        Ok(settled.ok_or(MiyaError::ArithmeticError)?)
+    }
+    
+    // Serialized in circuit order
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let public_amount = self.public_amount()?;
+        
+        let mut bytes = Vec::with_capacity(32 * 5 + 8 + 32);
+        bytes.extend_from_slice(&self.root);
+        for nullifier in self.input_nullifiers.iter() {
+            bytes.extend_from_slice(nullifier);
+        }
+        for commitment in self.output_commitments.iter() {
+            bytes.extend_from_slice(commitment);
+        }
+        bytes.extend_from_slice(&public_amount.to_le_bytes());
+        bytes.extend_from_slice(&ext_data_hash(&self.recipient, &self.relayer, self.ext_amount, self.fee));
+        Ok(bytes)
+    }
+}
+
+#[account]
+pub struct ShieldedPool {
+    pub authority: Pubkey,        // Authority allowed to admin the pool
+    pub token_mint: Pubkey,       // The token mint address
+    pub verifier: Pubkey,         // zk engine verifier for the join-split circuit
+    pub next_leaf_index: u64,     // Tree index assigned to the next output commitment
+    pub filled_subtrees: [[u8; 32]; Self::DEPTH], // Rightmost filled node per level
+    pub roots: [[u8; 32]; Self::ROOT_HISTORY_SIZE], // Recent roots, so proofs stay valid while others transact
+    pub current_root_index: u8,   // Slot of the latest root in `roots`
+    pub total_value: u64,         // Tokens owed to unspent notes
+    pub is_active: bool,          // Whether the pool is active or paused
+    pub bump: u8,                 // Bump seed for PDA
+}
+
+impl ShieldedPool {
+    pub const DEPTH: usize = 20;
+    pub const ROOT_HISTORY_SIZE: usize = 30;
+    
+    pub const LEN: usize = 32 + 32 + 32 + 8 + 32 * Self::DEPTH + 32 * Self::ROOT_HISTORY_SIZE + 1 + 8 + 1 + 1;
+    
+    // Root of a tree whose leaves are all zero
+    pub fn empty_root() -> [u8; 32] {
+        let mut zero = [0u8; 32];
+        for _ in 0..Self::DEPTH {
+            zero = keccak::hashv(&[&zero, &zero]).0;
+        }
+        zero
+    }
+    
+    pub fn current_root(&self) -> [u8; 32] {
+        self.roots[self.current_root_index as usize]
+    }
+    
+    pub fn is_known_root(&self, root: &[u8; 32]) -> bool {
+        *root != [0u8; 32] && self.roots.contains(root)
+    }
+    
+    // Append an output commitment to the incremental keccak Merkle tree
+    pub fn insert(&mut self, leaf: [u8; 32]) -> Result<u64> {
+        let leaf_index = self.next_leaf_index;
+        require!(leaf_index < (1 << Self::DEPTH), MiyaError::ShieldedTreeFull);
+        
+        let mut index = leaf_index;
+        let mut current = leaf;
+        let mut zero = [0u8; 32];
+        for level in 0..Self::DEPTH {
+            let (left, right) = if index % 2 == 0 {
+                self.filled_subtrees[level] = current;
+                (current, zero)
+            } else {
+                (self.filled_subtrees[level], current)
+            };
+            current = keccak::hashv(&[&left, &right]).0;
+            zero = keccak::hashv(&[&zero, &zero]).0;
+            index /= 2;
+        }
+        
+        self.current_root_index = ((self.current_root_index as usize + 1) % Self::ROOT_HISTORY_SIZE) as u8;
+        self.roots[self.current_root_index as usize] = current;
+        self.next_leaf_index = leaf_index.checked_add(1).ok_or(MiyaError::ArithmeticError)?;
+        
+        Ok(leaf_index)
+    }
+}
+
+// Marks a fixed-denomination note as withdrawn
+#[account]
+pub struct PoolNullifier {
+    pub pool: Pubkey,
+    pub nullifier: [u8; 32],
+}
+
+impl PoolNullifier {
+    pub const LEN: usize = 32 + 32;
+}
+
+#[account]
+pub struct ShieldedNullifier {
+    pub pool: Pubkey,
+    pub nullifier: [u8; 32],
+}
+
+impl ShieldedNullifier {
+    pub const LEN: usize = 32 + 32;
+}
+
+#[account]
+pub struct Relayer {
+    pub owner: Pubkey,            // Relayer key that receives fees
+    pub stake: u64,               // Lamports bonded on this account
+    pub fee_bps: u16,             // Advertised fee, in basis points of the denomination
+    pub is_active: bool,          // Whether the relayer accepts withdrawals
+    pub deactivated_at: i64,      // When the relayer last went inactive
+    pub bump: u8,                 // Bump seed for PDA
+}
+
+impl Relayer {
+    pub const MIN_STAKE: u64 = 1_000_000_000;
+    pub const UNBONDING_PERIOD: i64 = 7 * 24 * 60 * 60;
+    
+    pub const LEN: usize = 32 + 8 + 2 + 1 + 8 + 1;
+}
+
+#[account]
+pub struct SolVault {
+    pub pool: Pubkey,             // Native pool whose lamports this vault holds
+    pub bump: u8,                 // Bump seed for PDA
+}
+
+impl SolVault {
+    pub const LEN: usize = 32 + 1;
 }
 
 #[derive(Debug)]
@@ -340,6 +1989,8 @@ struct DepositNote {
 pub struct DepositEvent {
     pool: Pubkey,
     commitment: [u8; 32],
+    leaf_index: u64,
+    anonymity_set: u64,
     timestamp: i64,
 }
 
@@ -348,9 +1999,84 @@ pub struct WithdrawEvent {
     pool: Pubkey,
     nullifier: [u8; 32],
     recipient: Pubkey,
+    anonymity_set: u64,
     timestamp: i64,
 }
 
+#[event]
+pub struct ShieldedTransactionEvent {
+    pub pool: Pubkey,
+    pub input_nullifiers: [[u8; 32]; 2],
+    pub output_commitments: [[u8; 32]; 2],
+    pub leaf_indices: [u64; 2],
+    pub ext_amount: i64,
+    pub root: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DenominationPoolsCreatedEvent {
+    pub token_mint: Pubkey,
+    pub denominations: Vec<u64>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RelayerRegisteredEvent {
+    pub relayer: Pubkey,
+    pub stake: u64,
+    pub fee_bps: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RelayerUpdatedEvent {
+    pub relayer: Pubkey,
+    pub fee_bps: u16,
+    pub is_active: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RelayerSlashedEvent {
+    pub relayer: Pubkey,
+    pub amount: u64,
+    pub remaining_stake: u64,
+    pub recipient: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RelayerDeregisteredEvent {
+    pub relayer: Pubkey,
+    pub stake: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AuthorityTransferProposedEvent {
+    pub pool: Pubkey,
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AuthorityTransferredEvent {
+    pub pool: Pubkey,
+    pub old_authority: Pubkey,
+    pub new_authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AuthorityTransferCanceledEvent {
+    pub pool: Pubkey,
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+    pub timestamp: i64,
+}
+
 #[error_code]
 pub enum MiyaError {
     #[msg("Pool is not active")]
@@ -376,4 +2102,214 @@ pub enum MiyaError {
     
     #[msg("Arithmetic error")]
     ArithmeticError,
-} 
\ No newline at end of file
+    
+    #[msg("No authority transfer is pending")]
+    NoPendingAuthority,
+    
+    #[msg("Pool does not hold native SOL")]
+    NotNativePool,
+    
+    #[msg("Pool vault balance is insufficient")]
+    InsufficientVaultBalance,
+    
+    #[msg("Relayer account was not provided")]
+    MissingRelayerAccount,
+    
+    #[msg("Token mint has an unsupported Token-2022 extension")]
+    UnsupportedMintExtension,
+    
+    #[msg("Pool received less than the deposit amount")]
+    DepositAmountMismatch,
+    
+    #[msg("Pool vault does not cover outstanding deposits")]
+    VaultInvariantViolated,
+    
+    #[msg("Fee must not exceed 10000 basis points")]
+    InvalidFeeBps,
+    
+    #[msg("Relayer stake is below the minimum")]
+    InsufficientRelayerStake,
+    
+    #[msg("Relayer fee exceeds the allowed maximum")]
+    RelayerFeeTooHigh,
+    
+    #[msg("Relayer is not registered")]
+    RelayerNotRegistered,
+    
+    #[msg("Relayer is not active")]
+    RelayerInactive,
+    
+    #[msg("Relayer must be deactivated first")]
+    RelayerStillActive,
+    
+    #[msg("Relayer stake is still unbonding")]
+    UnbondingPeriodNotElapsed,
+    
+    #[msg("Fee and refund must be set if and only if a relayer is set")]
+    RelayerFeeMismatch,
+    
+    #[msg("Relayer account does not match the proven relayer")]
+    InvalidRelayerAccount,
+    
+    #[msg("Native pools do not support refunds")]
+    RefundNotSupported,
+    
+    #[msg("Recipient token account is not owned by the recipient")]
+    InvalidRecipientAccount,
+    
+    #[msg("Denominations do not match the pool accounts provided")]
+    InvalidDenominations,
+    
+    #[msg("Denomination is already registered for this mint")]
+    DuplicateDenomination,
+    
+    #[msg("Denomination registry is full")]
+    TooManyDenominations,
+    
+    #[msg("Pool account does not match the expected address")]
+    InvalidPoolAccount,
+    
+    #[msg("Merkle root is not in the recent root history")]
+    UnknownMerkleRoot,
+    
+    #[msg("Shielded pool tree is full")]
+    ShieldedTreeFull,
+    
+    #[msg("Token account for the public amount was not provided")]
+    MissingTokenAccount,
+    
+    #[msg("Slash amount must be greater than zero")]
+    InvalidSlashAmount,
+    
+    #[msg("Only Groth16 verifiers are supported")]
+    UnsupportedVerifier,
+    
+    #[msg("Verifier is not the pool's zk engine verifier")]
+    InvalidVerifier,
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn join_split(ext_amount: i64, fee: u64, relayer: Option<Pubkey>) -> JoinSplitPublicInputs {
+        JoinSplitPublicInputs {
+            root: [0; 32],
+            input_nullifiers: [[1; 32], [2; 32]],
+            output_commitments: [[3; 32], [4; 32]],
+            ext_amount,
+            fee,
+            recipient: Pubkey::new_unique(),
+            relayer,
+        }
+    }
+    
+    #[test]
+    fn relayed_withdrawal_pays_recipient_and_relayer_from_the_notes() {
+        let total_value = 1_000;
+        let inputs = join_split(-400, 25, Some(Pubkey::new_unique()));
+        
+        // The recipient gets the full external amount and the relayer its fee on top
+        let recipient_amount = inputs.ext_amount.unsigned_abs();
+        let relayer_amount = inputs.fee;
+        let vault_outflow = recipient_amount + relayer_amount;
+        
+        let settled = inputs.settled_total_value(total_value).unwrap();
+        assert_eq!(recipient_amount, 400);
+        assert_eq!(settled, 575);
+        assert_eq!(total_value - settled, vault_outflow);
+    }
+    
+    #[test]
+    fn settles_the_public_amount_net_of_the_fee() {
+        let cases = [
+            (0u64, 500i64, 0u64, Some(500u64)),
+            (0, 500, 20, Some(480)),
+            (1_000, -1_000, 0, Some(0)),
+            (1_000, -980, 20, Some(0)),
+            (1_000, -990, 20, None),
+            (1_000, 0, 20, Some(980)),
+        ];
+        
+        for &(total_value, ext_amount, fee, expected) in cases.iter() {
+            let relayer = if fee > 0 { Some(Pubkey::new_unique()) } else { None };
+            let settled = join_split(ext_amount, fee, relayer).settled_total_value(total_value).ok();
+            assert_eq!(settled, expected, "ext_amount {}, fee {}", ext_amount, fee);
+        }
+    }
+    
+    fn empty_shielded_pool() -> ShieldedPool {
+        let mut roots = [[0; 32]; ShieldedPool::ROOT_HISTORY_SIZE];
+        roots[0] = ShieldedPool::empty_root();
+        ShieldedPool {
+            authority: Pubkey::default(),
+            token_mint: Pubkey::default(),
+            verifier: Pubkey::default(),
+            next_leaf_index: 0,
+            filled_subtrees: [[0; 32]; ShieldedPool::DEPTH],
+            roots,
+            current_root_index: 0,
+            total_value: 0,
+            is_active: true,
+            bump: 0,
+        }
+    }
+    
+    // Root of the full tree with the given leaves followed by zero leaves, hashing
+    // only the filled part and padding each level with that level's zero node
+    fn naive_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+        let mut level = leaves.to_vec();
+        let mut zero = [0u8; 32];
+        for _ in 0..ShieldedPool::DEPTH {
+            if level.len() % 2 == 1 {
+                level.push(zero);
+            }
+            level = level
+                .chunks(2)
+                .map(|pair| keccak::hashv(&[&pair[0], &pair[1]]).0)
+                .collect();
+            zero = keccak::hashv(&[&zero, &zero]).0;
+        }
+        level.first().copied().unwrap_or(zero)
+    }
+    
+    #[test]
+    fn shielded_pool_insert_matches_full_tree() {
+        let mut pool = empty_shielded_pool();
+        assert_eq!(pool.current_root(), naive_root(&[]));
+        
+        let mut leaves = Vec::new();
+        for i in 1..=9u8 {
+            let leaf = keccak::hash(&[i]).0;
+            assert_eq!(pool.insert(leaf).unwrap(), (i - 1) as u64);
+            leaves.push(leaf);
+            assert_eq!(pool.current_root(), naive_root(&leaves), "root after {} leaves", i);
+        }
+        assert_eq!(pool.next_leaf_index, 9);
+    }
+    
+    #[test]
+    fn shielded_pool_remembers_only_recent_roots() {
+        let mut pool = empty_shielded_pool();
+        let empty_root = pool.current_root();
+        assert!(pool.is_known_root(&empty_root));
+        assert!(!pool.is_known_root(&[0; 32]));
+        
+        // One insert more than the history holds wraps the ring buffer past its start
+        let inserts = ShieldedPool::ROOT_HISTORY_SIZE + 1;
+        let mut roots = Vec::new();
+        for i in 0..inserts {
+            pool.insert(keccak::hash(&i.to_le_bytes()).0).unwrap();
+            roots.push(pool.current_root());
+        }
+        assert_eq!(pool.current_root_index as usize, inserts % ShieldedPool::ROOT_HISTORY_SIZE);
+        assert_eq!(pool.current_root(), roots[inserts - 1]);
+        
+        // The empty root and the first insert's root were overwritten
+        assert!(!pool.is_known_root(&empty_root));
+        assert!(!pool.is_known_root(&roots[0]));
+        for (i, root) in roots.iter().enumerate().skip(1) {
+            assert!(pool.is_known_root(root), "root after {} inserts", i + 1);
+        }
+    }
+}
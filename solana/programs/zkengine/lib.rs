@@ -9,6 +9,7 @@ pub mod miya_zkengine {
     pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
         let engine = &mut ctx.accounts.engine;
         engine.authority = ctx.accounts.authority.key();
+        engine.pending_authority = None;
         engine.initialized = true;
         engine.verifier_count = 0;
         
@@ -73,9 +74,7 @@ pub mod miya_zkengine {
         // Ensure the verifier is active
         require!(verifier.is_active, ZkEngineError::VerifierInactive);
         
-        // This is where the actual verification would occur
-        // In a real system, this would call into a native program or verify on-chain
-        // For this prototype, we'll just simulate success
+        let success = check_proof(verifier.verifier_type, &proof_data, &public_inputs)?;
         
         // Record the verification
         verifier.verification_count += 1;
@@ -83,13 +82,13 @@ pub mod miya_zkengine {
         // Emit verification event
         emit!(VerificationEvent {
             verifier: verifier.key(),
-            success: true,
+            success,
             timestamp: Clock::get()?.unix_timestamp,
         });
         
-        msg!("Proof verification successful");
+        msg!("Proof verification result: {}", success);
         
-        Ok(true)
+        Ok(success)
     }
 
     pub fn upgrade_engine(
@@ -111,6 +110,84 @@ pub mod miya_zkengine {
         
         Ok(())
     }
+
+    pub fn transfer_engine_authority(
+        ctx: Context<UpgradeEngine>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        let engine = &mut ctx.accounts.engine;
+        
+        // Only the current engine authority can transfer authority
+        require!(
+            ctx.accounts.authority.key() == engine.authority,
+            ZkEngineError::Unauthorized
+        );
+        
+        // The transfer only completes once the new authority accepts it
+        engine.pending_authority = Some(new_authority);
+        
+        emit!(AuthorityTransferProposedEvent {
+            engine: engine.key(),
+            authority: engine.authority,
+            pending_authority: new_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Engine authority transfer proposed to: {}", new_authority);
+        
+        Ok(())
+    }
+
+    pub fn accept_engine_authority(ctx: Context<AcceptEngineAuthority>) -> Result<()> {
+        let engine = &mut ctx.accounts.engine;
+        let new_authority = ctx.accounts.new_authority.key();
+        
+        // Only the pending authority can accept the transfer
+        let pending_authority = engine.pending_authority.ok_or(ZkEngineError::NoPendingAuthority)?;
+        require!(
+            new_authority == pending_authority,
+            ZkEngineError::Unauthorized
+        );
+        
+        // Update the authority
+        let old_authority = engine.authority;
+        engine.authority = new_authority;
+        engine.pending_authority = None;
+        
+        emit!(AuthorityTransferredEvent {
+            engine: engine.key(),
+            old_authority,
+            new_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Engine authority transferred to: {}", new_authority);
+        
+        Ok(())
+    }
+
+    pub fn cancel_engine_authority_transfer(ctx: Context<UpgradeEngine>) -> Result<()> {
+        let engine = &mut ctx.accounts.engine;
+        
+        // Only the current engine authority can cancel a pending transfer
+        require!(
+            ctx.accounts.authority.key() == engine.authority,
+            ZkEngineError::Unauthorized
+        );
+        
+        let pending_authority = engine.pending_authority.take().ok_or(ZkEngineError::NoPendingAuthority)?;
+        
+        emit!(AuthorityTransferCanceledEvent {
+            engine: engine.key(),
+            authority: engine.authority,
+            pending_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Engine authority transfer canceled");
+        
+        Ok(())
+    }
 }
 
 #[derive(Accounts)]
@@ -144,7 +221,7 @@ pub struct RegisterVerifier<'info> {
     )]
     pub verifier: Account<'info, Verifier>,
     
-    /// The authority of the verifier
+    /// CHECK: The authority of the verifier, only used to derive the verifier PDA
     pub verifier_key: AccountInfo<'info>,
     
     #[account(mut)]
@@ -179,16 +256,25 @@ pub struct UpgradeEngine<'info> {
     pub authority: Signer<'info>,
 }
 
+#[derive(Accounts)]
+pub struct AcceptEngineAuthority<'info> {
+    #[account(mut, seeds = [b"miya_zkengine"], bump)]
+    pub engine: Account<'info, ZkEngine>,
+    
+    pub new_authority: Signer<'info>,
+}
+
 #[account]
 pub struct ZkEngine {
     pub authority: Pubkey,
+    pub pending_authority: Option<Pubkey>,
     pub initialized: bool,
     pub version: u32,
     pub verifier_count: u32,
 }
 
 impl ZkEngine {
-    pub const LEN: usize = 32 + 1 + 4 + 4;
+    pub const LEN: usize = 32 + 33 + 1 + 4 + 4;
 }
 
 #[account]
@@ -203,6 +289,13 @@ impl Verifier {
     pub const LEN: usize = 32 + 1 + 1 + 8;
 }
 
+// No proving system is wired in yet. Verification reports itself unavailable
+// instead of rejecting every proof, so callers fail closed with an error that
+// tells a disabled feature apart from a bad proof.
+fn check_proof(_verifier_type: VerifierType, _proof_data: &[u8], _public_inputs: &[u8]) -> Result<bool> {
+    err!(ZkEngineError::ProofVerificationUnavailable)
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
 pub enum VerifierType {
     Groth16 = 0,
@@ -218,6 +311,30 @@ pub struct VerificationEvent {
     pub timestamp: i64,
 }
 
+#[event]
+pub struct AuthorityTransferProposedEvent {
+    pub engine: Pubkey,
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AuthorityTransferredEvent {
+    pub engine: Pubkey,
+    pub old_authority: Pubkey,
+    pub new_authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AuthorityTransferCanceledEvent {
+    pub engine: Pubkey,
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+    pub timestamp: i64,
+}
+
 #[error_code]
 pub enum ZkEngineError {
     #[msg("Unauthorized operation")]
@@ -228,4 +345,10 @@ pub enum ZkEngineError {
     
     #[msg("Proof verification failed")]
     VerificationFailed,
+    
+    #[msg("No authority transfer is pending")]
+    NoPendingAuthority,
+    
+    #[msg("No proving system is available to verify proofs")]
+    ProofVerificationUnavailable,
 } 
\ No newline at end of file
@@ -0,0 +1,1574 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import { PublicKey, Keypair, SystemProgram, TransactionInstruction } from "@solana/web3.js";
+import {
+  createMint,
+  createAssociatedTokenAccount,
+  getOrCreateAssociatedTokenAccount,
+  createTransferInstruction,
+  getAccount,
+  mintTo,
+  TOKEN_PROGRAM_ID,
+} from "@solana/spl-token";
+import { assert } from "chai";
+import { keccak_256 } from "js-sha3";
+import { MiyaGovernance } from "../target/types/miya_governance";
+import { MiyaMixer } from "../target/types/miya_mixer";
+import { MiyaBridge } from "../target/types/miya_bridge";
+import { MiyaZkengine } from "../target/types/miya_zkengine";
+
+const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
+
+describe("miya_governance", () => {
+  // Configure the client to use the local cluster
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const governance = anchor.workspace.MiyaGovernance as Program<MiyaGovernance>;
+  const mixer = anchor.workspace.MiyaMixer as Program<MiyaMixer>;
+  const bridge = anchor.workspace.MiyaBridge as Program<MiyaBridge>;
+  const zkengine = anchor.workspace.MiyaZkengine as Program<MiyaZkengine>;
+
+  const authority = Keypair.generate();
+  const daoName = "miya-protocol-dao";
+  const depositAmount = new anchor.BN(1_000_000_000);
+  const votingPeriod = new anchor.BN(2);
+  const lockAmount = new anchor.BN(500_000_000);
+  const maxLockDuration = 4 * 365 * 24 * 60 * 60;
+
+  let governanceMint: PublicKey;
+  let proposerTokenAccount: PublicKey;
+  let daoKey: PublicKey;
+  let treasuryKey: PublicKey;
+  let voteEscrowKey: PublicKey;
+  let escrowVaultKey: PublicKey;
+  let poolKey: PublicKey;
+  let bridgeKey: PublicKey;
+  let tokenPairKey: PublicKey;
+  let engineKey: PublicKey;
+
+  const actionRecordAddress = async (proposalKey: PublicKey, index: number) => {
+    const indexBuffer = Buffer.alloc(2);
+    indexBuffer.writeUInt16LE(index);
+    const [actionRecord] = await PublicKey.findProgramAddress(
+      [Buffer.from("miya_proposal_action"), proposalKey.toBuffer(), indexBuffer],
+      governance.programId
+    );
+    return actionRecord;
+  };
+
+  const voteRecordAddress = async (proposalKey: PublicKey, voter: PublicKey) => {
+    const [voteRecord] = await PublicKey.findProgramAddress(
+      [Buffer.from("miya_vote"), proposalKey.toBuffer(), voter.toBuffer()],
+      governance.programId
+    );
+    return voteRecord;
+  };
+
+  // Store an instruction signed by the treasury as a raw proposal action
+  const toProposalAction = (instruction: TransactionInstruction) => ({
+    programId: instruction.programId,
+    accounts: instruction.keys.map((key) => ({
+      pubkey: key.pubkey,
+      isSigner: key.isSigner,
+      isWritable: key.isWritable,
+    })),
+    data: instruction.data,
+  });
+
+  const treasuryTransfer = (recipient: PublicKey, lamports: number) =>
+    toProposalAction(SystemProgram.transfer({ fromPubkey: treasuryKey, toPubkey: recipient, lamports }));
+
+  // Proposers without an escrow pass null, which only meets a zero threshold
+  const createProposal = async (
+    voteType: any = { binary: {} },
+    options: string[] = [],
+    voteEscrow: PublicKey | null = voteEscrowKey
+  ) => {
+    const dao = await governance.account.dao.fetch(daoKey);
+    const [proposalKey] = await PublicKey.findProgramAddress(
+      [Buffer.from("miya_proposal"), daoKey.toBuffer(), dao.proposalCount.toArrayLike(Buffer, "le", 8)],
+      governance.programId
+    );
+
+    await governance.methods
+      .createProposal("MIYA governance proposal", "ipfs://miya/proposal", Array(32).fill(1), votingPeriod, voteType, options)
+      .accounts({
+        dao: daoKey,
+        proposal: proposalKey,
+        proposer: authority.publicKey,
+        voteEscrow,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([authority])
+      .rpc();
+
+    return proposalKey;
+  };
+
+  // Raw actions carry a program id, anything else is a typed protocol action
+  const addAction = async (proposalKey: PublicKey, action: any, optionIndex = 0) => {
+    const proposal = await governance.account.proposal.fetch(proposalKey);
+    const actionRecord = await actionRecordAddress(proposalKey, proposal.nextActionIndex);
+
+    if (action.programId) {
+      await governance.methods
+        .addProposalAction(optionIndex, action)
+        .accounts({
+          proposal: proposalKey,
+          actionRecord,
+          proposer: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([authority])
+        .rpc();
+    } else {
+      await governance.methods
+        .addProtocolAction(optionIndex, action)
+        .accounts({
+          dao: daoKey,
+          proposal: proposalKey,
+          treasury: treasuryKey,
+          actionRecord,
+          proposer: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([authority])
+        .rpc();
+    }
+
+    return actionRecord;
+  };
+
+  const updateDaoSettings = (settings: {
+    minProposalWeight?: anchor.BN;
+    proposalDeposit?: anchor.BN;
+    quorumVotes?: anchor.BN;
+    executionDelay?: anchor.BN;
+    votingStrategy?: any;
+    revealPeriod?: anchor.BN;
+    anonymousVoting?: boolean;
+  }) =>
+    governance.methods
+      .updateDaoSettings(
+        null,
+        null,
+        null,
+        settings.minProposalWeight ?? null,
+        settings.proposalDeposit ?? null,
+        settings.quorumVotes ?? null,
+        settings.executionDelay ?? null,
+        settings.votingStrategy ?? null,
+        settings.revealPeriod ?? null,
+        settings.anonymousVoting ?? null
+      )
+      .accounts({ dao: daoKey, authority: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+  const cancelProposal = (proposalKey: PublicKey) =>
+    governance.methods
+      .cancelProposal()
+      .accounts({
+        dao: daoKey,
+        proposal: proposalKey,
+        canceler: authority.publicKey,
+        treasury: treasuryKey,
+        proposer: authority.publicKey,
+      })
+      .signers([authority])
+      .rpc();
+
+  const closeProposal = async (proposalKey: PublicKey) =>
+    governance.methods
+      .closeProposal()
+      .accounts({
+        proposal: proposalKey,
+        rewardPool: (
+          await PublicKey.findProgramAddress(
+            [Buffer.from("miya_reward_pool"), proposalKey.toBuffer()],
+            governance.programId
+          )
+        )[0],
+        treasury: treasuryKey,
+        proposer: authority.publicKey,
+      })
+      .signers([authority])
+      .rpc();
+
+  const signOff = (proposalKey: PublicKey) =>
+    governance.methods
+      .signOffProposal()
+      .accounts({ dao: daoKey, proposal: proposalKey, proposer: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+  const castVote = async (proposalKey: PublicKey, vote: any) =>
+    governance.methods
+      .castVote(vote)
+      .accounts({
+        dao: daoKey,
+        proposal: proposalKey,
+        voter: authority.publicKey,
+        voteRecord: await voteRecordAddress(proposalKey, authority.publicKey),
+        voteEscrow: voteEscrowKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([authority])
+      .rpc();
+
+  const executeAction = async (proposalKey: PublicKey, actionRecord: PublicKey) => {
+    const record = await governance.account.proposalActionRecord.fetch(actionRecord);
+    const remainingAccounts = record.action.accounts
+      .filter((account) => !account.pubkey.equals(treasuryKey))
+      .map((account) => ({ pubkey: account.pubkey, isSigner: false, isWritable: account.isWritable }));
+    remainingAccounts.push({ pubkey: record.action.programId, isSigner: false, isWritable: false });
+
+    return governance.methods
+      .executeProposalAction()
+      .accounts({
+        dao: daoKey,
+        proposal: proposalKey,
+        actionRecord,
+        treasury: treasuryKey,
+        executor: provider.wallet.publicKey,
+      })
+      .remainingAccounts(remainingAccounts)
+      .rpc();
+  };
+
+  // Wait for the voting period to end, then finalize and queue the proposal
+  const finalizeAndQueue = async (proposalKey: PublicKey) => {
+    await sleep((votingPeriod.toNumber() + 1) * 1000);
+
+    await governance.methods.finalizeProposal().accounts({ dao: daoKey, proposal: proposalKey }).rpc();
+    await governance.methods.queueProposal().accounts({ dao: daoKey, proposal: proposalKey }).rpc();
+  };
+
+  // Create, vote on and execute a binary proposal made of the given actions
+  const passProposal = async (actions: any[]) => {
+    const proposalKey = await createProposal();
+
+    const actionRecords: PublicKey[] = [];
+    for (const action of actions) {
+      actionRecords.push(await addAction(proposalKey, action));
+    }
+
+    await signOff(proposalKey);
+    await castVote(proposalKey, { for: {} });
+    await finalizeAndQueue(proposalKey);
+
+    for (const actionRecord of actionRecords) {
+      await executeAction(proposalKey, actionRecord);
+    }
+
+    return proposalKey;
+  };
+
+  before(async () => {
+    // Airdrop SOL to the authority
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(authority.publicKey, 10 * anchor.web3.LAMPORTS_PER_SOL)
+    );
+
+    // Create the governance token and the proposer's token account
+    governanceMint = await createMint(provider.connection, authority, authority.publicKey, null, 9);
+    proposerTokenAccount = await createAssociatedTokenAccount(
+      provider.connection,
+      authority,
+      governanceMint,
+      authority.publicKey
+    );
+    await mintTo(provider.connection, authority, governanceMint, proposerTokenAccount, authority, 1_000_000_000);
+
+    // Initialize the DAO and fund its treasury
+    [daoKey] = await PublicKey.findProgramAddress(
+      [Buffer.from("miya_dao"), Buffer.from(daoName)],
+      governance.programId
+    );
+    [treasuryKey] = await PublicKey.findProgramAddress(
+      [Buffer.from("miya_treasury"), daoKey.toBuffer()],
+      governance.programId
+    );
+    await governance.methods
+      .initializeDao(daoName, new anchor.BN(1), new anchor.BN(60))
+      .accounts({
+        dao: daoKey,
+        authority: authority.publicKey,
+        governanceMint,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([authority])
+      .rpc();
+    await governance.methods
+      .depositToTreasury(new anchor.BN(anchor.web3.LAMPORTS_PER_SOL))
+      .accounts({
+        dao: daoKey,
+        treasury: treasuryKey,
+        depositor: authority.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([authority])
+      .rpc();
+
+    // Lock governance tokens for a year to get voting power
+    [voteEscrowKey] = await PublicKey.findProgramAddress(
+      [Buffer.from("miya_escrow"), daoKey.toBuffer(), authority.publicKey.toBuffer()],
+      governance.programId
+    );
+    [escrowVaultKey] = await PublicKey.findProgramAddress(
+      [Buffer.from("miya_escrow_vault"), voteEscrowKey.toBuffer()],
+      governance.programId
+    );
+    const lockEnd = new anchor.BN(Math.floor(Date.now() / 1000) + 365 * 24 * 60 * 60);
+    await governance.methods
+      .lockTokens(lockAmount, lockEnd)
+      .accounts({
+        dao: daoKey,
+        voteEscrow: voteEscrowKey,
+        escrowVault: escrowVaultKey,
+        governanceMint,
+        owner: authority.publicKey,
+        ownerTokenAccount: proposerTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      })
+      .signers([authority])
+      .rpc();
+  });
+
+  it("Weights votes by the decayed escrow balance at the proposal snapshot", async () => {
+    const proposalKey = await passProposal([treasuryTransfer(authority.publicKey, 1)]);
+
+    const proposal = await governance.account.proposal.fetch(proposalKey);
+    const escrow = await governance.account.voteEscrow.fetch(voteEscrowKey);
+    const [voteRecord] = await PublicKey.findProgramAddress(
+      [Buffer.from("miya_vote"), proposalKey.toBuffer(), authority.publicKey.toBuffer()],
+      governance.programId
+    );
+    const record = await governance.account.voteRecord.fetch(voteRecord);
+
+    const remaining = escrow.lockEnd.sub(proposal.votingStartsAt);
+    const expected = escrow.amount.mul(remaining).div(new anchor.BN(maxLockDuration));
+    assert.equal(record.weight.toString(), expected.toString());
+    assert.isTrue(record.weight.lt(lockAmount));
+  });
+
+  it("Weights votes by the square root of voting power in quadratic mode", async () => {
+    await updateDaoSettings({ votingStrategy: { quadratic: {} } });
+    const proposalKey = await passProposal([treasuryTransfer(authority.publicKey, 1)]);
+    await updateDaoSettings({ votingStrategy: { linear: {} } });
+
+    const proposal = await governance.account.proposal.fetch(proposalKey);
+    assert.deepEqual(proposal.votingStrategy, { quadratic: {} });
+
+    const escrow = await governance.account.voteEscrow.fetch(voteEscrowKey);
+    const [voteRecord] = await PublicKey.findProgramAddress(
+      [Buffer.from("miya_vote"), proposalKey.toBuffer(), authority.publicKey.toBuffer()],
+      governance.programId
+    );
+    const record = await governance.account.voteRecord.fetch(voteRecord);
+
+    // Linear voting power at the snapshot, and its integer square root as the reference
+    const power = escrow.amount
+      .mul(escrow.lockEnd.sub(proposal.votingStartsAt))
+      .div(new anchor.BN(maxLockDuration));
+    const weight = record.weight;
+    assert.isTrue(weight.mul(weight).lte(power));
+    assert.isTrue(weight.addn(1).mul(weight.addn(1)).gt(power));
+  });
+
+  it("Keeps tokens locked until the lock ends", async () => {
+    try {
+      await governance.methods
+        .unlockTokens()
+        .accounts({
+          dao: daoKey,
+          voteEscrow: voteEscrowKey,
+          escrowVault: escrowVaultKey,
+          owner: authority.publicKey,
+          ownerTokenAccount: proposerTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([authority])
+        .rpc();
+      assert.fail("Expected the unlock to be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "LockNotExpired");
+    }
+  });
+
+  it("Freezes proposals while the DAO is deactivated", async () => {
+    // One proposal still voting, one whose vote has ended and one queued for execution
+    const ended = await createProposal();
+    await addAction(ended, treasuryTransfer(authority.publicKey, 1));
+    await signOff(ended);
+    await castVote(ended, { for: {} });
+
+    const queued = await createProposal();
+    const queuedAction = await addAction(queued, treasuryTransfer(authority.publicKey, 1));
+    await signOff(queued);
+    await castVote(queued, { for: {} });
+    await finalizeAndQueue(queued);
+
+    const voting = await createProposal();
+    await addAction(voting, treasuryTransfer(authority.publicKey, 1));
+    await signOff(voting);
+
+    await governance.methods
+      .deactivateDao()
+      .accounts({ dao: daoKey, signer: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const frozen = [
+      () => passProposal([treasuryTransfer(authority.publicKey, 1)]),
+      () => castVote(voting, { for: {} }),
+      () => governance.methods.finalizeProposal().accounts({ dao: daoKey, proposal: ended }).rpc(),
+      () => executeAction(queued, queuedAction),
+    ];
+    for (const attempt of frozen) {
+      try {
+        await attempt();
+        assert.fail("Expected proposals to be frozen while the DAO is inactive");
+      } catch (err) {
+        assert.include(err.toString(), "DaoInactive");
+      }
+    }
+
+    await governance.methods
+      .reactivateDao()
+      .accounts({ dao: daoKey, signer: authority.publicKey })
+      .signers([authority])
+      .rpc();
+
+    const dao = await governance.account.dao.fetch(daoKey);
+    assert.isTrue(dao.isActive);
+
+    // The queued proposal picks up where it left off
+    await executeAction(queued, queuedAction);
+    assert.deepEqual((await governance.account.proposal.fetch(queued)).state, { executed: {} });
+  });
+
+  it("Holds SOL and token deposits in the treasury", async () => {
+    const amount = anchor.web3.LAMPORTS_PER_SOL / 2;
+    const balanceBefore = await provider.connection.getBalance(treasuryKey);
+    await governance.methods
+      .depositToTreasury(new anchor.BN(amount))
+      .accounts({
+        dao: daoKey,
+        treasury: treasuryKey,
+        depositor: authority.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([authority])
+      .rpc();
+    assert.equal(await provider.connection.getBalance(treasuryKey), balanceBefore + amount);
+
+    const treasuryTokenAccount = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      authority,
+      governanceMint,
+      treasuryKey,
+      true
+    );
+    await governance.methods
+      .depositTokensToTreasury(new anchor.BN(1_000))
+      .accounts({
+        dao: daoKey,
+        treasury: treasuryKey,
+        depositor: authority.publicKey,
+        depositorTokenAccount: proposerTokenAccount,
+        treasuryTokenAccount: treasuryTokenAccount.address,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([authority])
+      .rpc();
+
+    const tokenAccount = await getAccount(provider.connection, treasuryTokenAccount.address);
+    assert.equal(Number(tokenAccount.amount), Number(treasuryTokenAccount.amount) + 1_000);
+  });
+
+  it("Pays SOL and tokens out of the treasury through executed actions", async () => {
+    const recipient = Keypair.generate();
+    const recipientTokenAccount = await createAssociatedTokenAccount(
+      provider.connection,
+      authority,
+      governanceMint,
+      recipient.publicKey
+    );
+    const treasuryTokenAccount = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      authority,
+      governanceMint,
+      treasuryKey,
+      true
+    );
+
+    const lamports = anchor.web3.LAMPORTS_PER_SOL / 10;
+    const balanceBefore = await provider.connection.getBalance(treasuryKey);
+    await passProposal([
+      treasuryTransfer(recipient.publicKey, lamports),
+      toProposalAction(createTransferInstruction(treasuryTokenAccount.address, recipientTokenAccount, treasuryKey, 500)),
+    ]);
+
+    assert.equal(await provider.connection.getBalance(recipient.publicKey), lamports);
+    assert.equal(await provider.connection.getBalance(treasuryKey), balanceBefore - lamports);
+
+    const recipientAccount = await getAccount(provider.connection, recipientTokenAccount);
+    assert.equal(Number(recipientAccount.amount), 500);
+    const treasuryAccount = await getAccount(provider.connection, treasuryTokenAccount.address);
+    assert.equal(Number(treasuryAccount.amount), Number(treasuryTokenAccount.amount) - 500);
+  });
+
+  it("Moves weight between tallies when a vote is changed or relinquished", async () => {
+    const proposalKey = await createProposal();
+    await addAction(proposalKey, treasuryTransfer(authority.publicKey, 1));
+    await signOff(proposalKey);
+    await castVote(proposalKey, { for: {} });
+
+    const voteRecord = await voteRecordAddress(proposalKey, authority.publicKey);
+    const { weight } = await governance.account.voteRecord.fetch(voteRecord);
+    let proposal = await governance.account.proposal.fetch(proposalKey);
+    assert.equal(proposal.forVotes.toString(), weight.toString());
+
+    await governance.methods
+      .changeVote({ against: {} })
+      .accounts({
+        dao: daoKey,
+        proposal: proposalKey,
+        voter: authority.publicKey,
+        voteRecord,
+        voteEscrow: voteEscrowKey,
+      })
+      .signers([authority])
+      .rpc();
+    proposal = await governance.account.proposal.fetch(proposalKey);
+    assert.equal(proposal.forVotes.toString(), "0");
+    assert.equal(proposal.againstVotes.toString(), weight.toString());
+
+    await governance.methods
+      .relinquishVote()
+      .accounts({ dao: daoKey, proposal: proposalKey, voter: authority.publicKey, voteRecord })
+      .signers([authority])
+      .rpc();
+    proposal = await governance.account.proposal.fetch(proposalKey);
+    assert.equal(proposal.againstVotes.toString(), "0");
+
+    const record = await governance.account.voteRecord.fetch(voteRecord);
+    assert.isFalse(record.hasVoted);
+    assert.equal(record.weight.toString(), "0");
+  });
+
+  it("Edits drafts through per-action PDAs and closes their records once settled", async () => {
+    const proposalKey = await createProposal();
+    const first = await addAction(proposalKey, treasuryTransfer(authority.publicKey, 1));
+    const second = await addAction(proposalKey, treasuryTransfer(authority.publicKey, 2));
+    assert.isTrue(first.equals(await actionRecordAddress(proposalKey, 0)));
+    assert.isTrue(second.equals(await actionRecordAddress(proposalKey, 1)));
+
+    // Removing an action closes its record without reusing its index
+    await governance.methods
+      .removeProposalAction()
+      .accounts({ proposal: proposalKey, actionRecord: first, proposer: authority.publicKey })
+      .signers([authority])
+      .rpc();
+    assert.isNull(await provider.connection.getAccountInfo(first));
+
+    const third = await addAction(proposalKey, treasuryTransfer(authority.publicKey, 3));
+    assert.isTrue(third.equals(await actionRecordAddress(proposalKey, 2)));
+
+    let proposal = await governance.account.proposal.fetch(proposalKey);
+    assert.equal(proposal.actionCount, 2);
+    assert.equal(proposal.nextActionIndex, 3);
+
+    await signOff(proposalKey);
+    try {
+      await addAction(proposalKey, treasuryTransfer(authority.publicKey, 4));
+      assert.fail("Expected actions to be rejected after sign-off");
+    } catch (err) {
+      assert.include(err.toString(), "ProposalNotDraft");
+    }
+
+    const closeActionRecord = (actionRecord: PublicKey) =>
+      governance.methods
+        .closeActionRecord()
+        .accounts({ proposal: proposalKey, actionRecord, proposer: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+    // Records stay open while the proposal can still execute them
+    try {
+      await closeActionRecord(second);
+      assert.fail("Expected the record to stay open during voting");
+    } catch (err) {
+      assert.include(err.toString(), "ProposalNotClosable");
+    }
+
+    await cancelProposal(proposalKey);
+
+    // The proposal outlives its records so their rent can still be reclaimed
+    try {
+      await closeProposal(proposalKey);
+      assert.fail("Expected the proposal to stay open while it has action records");
+    } catch (err) {
+      assert.include(err.toString(), "ActionRecordsOpen");
+    }
+
+    await closeActionRecord(second);
+    await closeActionRecord(third);
+    assert.isNull(await provider.connection.getAccountInfo(second));
+    assert.isNull(await provider.connection.getAccountInfo(third));
+
+    proposal = await governance.account.proposal.fetch(proposalKey);
+    assert.equal(proposal.actionCount, 0);
+
+    await closeProposal(proposalKey);
+    assert.isNull(await provider.connection.getAccountInfo(proposalKey));
+  });
+
+  it("Requires locked voting power to create proposals", async () => {
+    // Without a threshold no escrow is needed
+    const proposalKey = await createProposal({ binary: {} }, [], null);
+    await cancelProposal(proposalKey);
+
+    await updateDaoSettings({ minProposalWeight: lockAmount.muln(2) });
+    try {
+      for (const voteEscrow of [voteEscrowKey, null]) {
+        try {
+          await createProposal({ binary: {} }, [], voteEscrow);
+          assert.fail("Expected the proposal to be rejected");
+        } catch (err) {
+          assert.include(err.toString(), "ProposalThresholdNotMet");
+        }
+      }
+    } finally {
+      await updateDaoSettings({ minProposalWeight: new anchor.BN(0) });
+    }
+  });
+
+  it("Rejects proposals whose title or content URI is too long", async () => {
+    const dao = await governance.account.dao.fetch(daoKey);
+    const [proposalKey] = await PublicKey.findProgramAddress(
+      [Buffer.from("miya_proposal"), daoKey.toBuffer(), dao.proposalCount.toArrayLike(Buffer, "le", 8)],
+      governance.programId
+    );
+    const cases = [
+      { title: "t".repeat(65), contentUri: "ipfs://miya/proposal", error: "TitleTooLong" },
+      { title: "MIYA governance proposal", contentUri: "u".repeat(201), error: "ContentUriTooLong" },
+    ];
+
+    for (const { title, contentUri, error } of cases) {
+      try {
+        await governance.methods
+          .createProposal(title, contentUri, Array(32).fill(1), votingPeriod, { binary: {} }, [])
+          .accounts({
+            dao: daoKey,
+            proposal: proposalKey,
+            proposer: authority.publicKey,
+            voteEscrow: voteEscrowKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([authority])
+          .rpc();
+        assert.fail("Expected the proposal to be rejected");
+      } catch (err) {
+        assert.include(err.toString(), error);
+      }
+    }
+  });
+
+  it("Refunds a withdrawn proposal's deposit and slashes one that misses quorum", async () => {
+    const deposit = anchor.web3.LAMPORTS_PER_SOL / 10;
+    await updateDaoSettings({
+      proposalDeposit: new anchor.BN(deposit),
+      quorumVotes: new anchor.BN("18446744073709551615"),
+    });
+
+    try {
+      // The proposer gets the deposit back when withdrawing while voting is open
+      const withdrawn = await createProposal();
+      assert.equal((await governance.account.proposal.fetch(withdrawn)).depositAmount.toNumber(), deposit);
+      const escrowedBefore = await provider.connection.getBalance(withdrawn);
+      const proposerBefore = await provider.connection.getBalance(authority.publicKey);
+      await cancelProposal(withdrawn);
+      assert.equal(await provider.connection.getBalance(withdrawn), escrowedBefore - deposit);
+      assert.isAbove(await provider.connection.getBalance(authority.publicKey), proposerBefore);
+      assert.equal((await governance.account.proposal.fetch(withdrawn)).depositAmount.toNumber(), 0);
+
+      // After voting ends the proposer can no longer cancel, and missing quorum slashes the deposit
+      const failing = await createProposal();
+      await addAction(failing, treasuryTransfer(authority.publicKey, 1));
+      await signOff(failing);
+      await castVote(failing, { for: {} });
+      await sleep((votingPeriod.toNumber() + 1) * 1000);
+
+      try {
+        await cancelProposal(failing);
+        assert.fail("Expected the late cancellation to be rejected");
+      } catch (err) {
+        assert.include(err.toString(), "VotingEnded");
+      }
+
+      await governance.methods.finalizeProposal().accounts({ dao: daoKey, proposal: failing }).rpc();
+      assert.deepEqual((await governance.account.proposal.fetch(failing)).state, { defeated: {} });
+
+      const treasuryBefore = await provider.connection.getBalance(treasuryKey);
+      await governance.methods
+        .settleProposalDeposit()
+        .accounts({ dao: daoKey, proposal: failing, treasury: treasuryKey, proposer: authority.publicKey })
+        .rpc();
+      assert.equal(await provider.connection.getBalance(treasuryKey), treasuryBefore + deposit);
+      assert.equal((await governance.account.proposal.fetch(failing)).depositAmount.toNumber(), 0);
+    } finally {
+      await updateDaoSettings({ proposalDeposit: new anchor.BN(0), quorumVotes: new anchor.BN(0) });
+    }
+  });
+
+  it("Holds queued proposals until the timelock elapses", async () => {
+    await updateDaoSettings({ executionDelay: new anchor.BN(3) });
+
+    try {
+      const proposalKey = await createProposal();
+      const actionRecord = await addAction(proposalKey, treasuryTransfer(authority.publicKey, 1));
+      await signOff(proposalKey);
+      await castVote(proposalKey, { for: {} });
+      await finalizeAndQueue(proposalKey);
+
+      const proposal = await governance.account.proposal.fetch(proposalKey);
+      assert.deepEqual(proposal.state, { queued: {} });
+      assert.isTrue(proposal.executableAt.gt(new anchor.BN(0)));
+
+      try {
+        await executeAction(proposalKey, actionRecord);
+        assert.fail("Expected execution to wait for the timelock");
+      } catch (err) {
+        assert.include(err.toString(), "TimelockNotElapsed");
+      }
+
+      await sleep(4000);
+      await executeAction(proposalKey, actionRecord);
+      assert.deepEqual((await governance.account.proposal.fetch(proposalKey)).state, { executed: {} });
+    } finally {
+      await updateDaoSettings({ executionDelay: new anchor.BN(0) });
+    }
+  });
+
+  it("Requires a timelock for the veto council and lets it veto queued proposals", async () => {
+    const councilMember = Keypair.generate();
+    const setVetoCouncil = (members: PublicKey[], threshold: number) =>
+      governance.methods
+        .setVetoCouncil(members, threshold)
+        .accounts({ dao: daoKey, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+    // The council cannot be installed, nor the delay lowered, without a minimum timelock
+    try {
+      await setVetoCouncil([councilMember.publicKey], 1);
+      assert.fail("Expected the council to be rejected without a timelock");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidExecutionDelay");
+    }
+
+    await updateDaoSettings({ executionDelay: new anchor.BN(60 * 60) });
+    await setVetoCouncil([councilMember.publicKey], 1);
+
+    try {
+      try {
+        await updateDaoSettings({ executionDelay: new anchor.BN(0) });
+        assert.fail("Expected the delay to stay above the council minimum");
+      } catch (err) {
+        assert.include(err.toString(), "InvalidExecutionDelay");
+      }
+
+      const proposalKey = await createProposal();
+      const actionRecord = await addAction(proposalKey, treasuryTransfer(authority.publicKey, 1));
+      await signOff(proposalKey);
+      await castVote(proposalKey, { for: {} });
+      await finalizeAndQueue(proposalKey);
+
+      try {
+        await executeAction(proposalKey, actionRecord);
+        assert.fail("Expected execution to wait for the timelock");
+      } catch (err) {
+        assert.include(err.toString(), "TimelockNotElapsed");
+      }
+
+      await governance.methods
+        .vetoProposal()
+        .accounts({ dao: daoKey, proposal: proposalKey, councilMember: councilMember.publicKey })
+        .signers([councilMember])
+        .rpc();
+      assert.deepEqual((await governance.account.proposal.fetch(proposalKey)).state, { vetoed: {} });
+
+      try {
+        await executeAction(proposalKey, actionRecord);
+        assert.fail("Expected a vetoed proposal not to execute");
+      } catch (err) {
+        assert.include(err.toString(), "ProposalNotQueued");
+      }
+    } finally {
+      await setVetoCouncil([], 0);
+      await updateDaoSettings({ executionDelay: new anchor.BN(0) });
+    }
+  });
+
+  it("Tallies single-choice votes and executes only the winning option's actions", async () => {
+    const recipients = [Keypair.generate(), Keypair.generate()];
+    const lamports = anchor.web3.LAMPORTS_PER_SOL / 100;
+    const proposalKey = await createProposal({ singleChoice: {} }, ["Option A", "Option B"]);
+    const actionRecords = [];
+    for (const [index, recipient] of recipients.entries()) {
+      actionRecords.push(await addAction(proposalKey, treasuryTransfer(recipient.publicKey, lamports), index));
+    }
+    await signOff(proposalKey);
+
+    // A single-choice vote picks exactly one option
+    try {
+      await castVote(proposalKey, { choice: { 0: Buffer.from([0, 1]) } });
+      assert.fail("Expected a vote for two options to be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidVote");
+    }
+    await castVote(proposalKey, { choice: { 0: Buffer.from([1]) } });
+
+    const { weight } = await governance.account.voteRecord.fetch(
+      await voteRecordAddress(proposalKey, authority.publicKey)
+    );
+    let proposal = await governance.account.proposal.fetch(proposalKey);
+    assert.equal(proposal.options[0].voteWeight.toString(), "0");
+    assert.equal(proposal.options[1].voteWeight.toString(), weight.toString());
+    assert.equal(proposal.forVotes.toString(), weight.toString());
+
+    await finalizeAndQueue(proposalKey);
+    proposal = await governance.account.proposal.fetch(proposalKey);
+    assert.equal(proposal.winningOption, 1);
+
+    try {
+      await executeAction(proposalKey, actionRecords[0]);
+      assert.fail("Expected the losing option's action to be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "ActionNotInWinningOption");
+    }
+    await executeAction(proposalKey, actionRecords[1]);
+
+    proposal = await governance.account.proposal.fetch(proposalKey);
+    assert.deepEqual(proposal.state, { executed: {} });
+    assert.equal(await provider.connection.getBalance(recipients[0].publicKey), 0);
+    assert.equal(await provider.connection.getBalance(recipients[1].publicKey), lamports);
+  });
+
+  it("Gives every approved option the voter's full weight", async () => {
+    const proposalKey = await createProposal({ approval: {} }, ["Option A", "Option B", "Option C"]);
+    await signOff(proposalKey);
+    await castVote(proposalKey, { choice: { 0: Buffer.from([0, 2]) } });
+
+    const { weight } = await governance.account.voteRecord.fetch(
+      await voteRecordAddress(proposalKey, authority.publicKey)
+    );
+    let proposal = await governance.account.proposal.fetch(proposalKey);
+    assert.equal(proposal.options[0].voteWeight.toString(), weight.toString());
+    assert.equal(proposal.options[1].voteWeight.toString(), "0");
+    assert.equal(proposal.options[2].voteWeight.toString(), weight.toString());
+    assert.equal(proposal.forVotes.toString(), weight.toString());
+
+    // Two options tied for the lead, so there is no winner
+    await sleep((votingPeriod.toNumber() + 1) * 1000);
+    await governance.methods.finalizeProposal().accounts({ dao: daoKey, proposal: proposalKey }).rpc();
+    proposal = await governance.account.proposal.fetch(proposalKey);
+    assert.deepEqual(proposal.state, { defeated: {} });
+    assert.isNull(proposal.winningOption);
+  });
+
+  it("Keeps voting power on open proposals when a lock is topped up mid-vote", async () => {
+    const proposalKey = await createProposal();
+    await addAction(proposalKey, treasuryTransfer(authority.publicKey, 1));
+    await signOff(proposalKey);
+
+    const topUp = async () => {
+      const escrow = await governance.account.voteEscrow.fetch(voteEscrowKey);
+      await governance.methods
+        .lockTokens(new anchor.BN(1_000), escrow.lockEnd.addn(60))
+        .accounts({
+          dao: daoKey,
+          voteEscrow: voteEscrowKey,
+          escrowVault: escrowVaultKey,
+          governanceMint,
+          owner: authority.publicKey,
+          ownerTokenAccount: proposerTokenAccount,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .signers([authority])
+        .rpc();
+    };
+
+    const escrowBefore = await governance.account.voteEscrow.fetch(voteEscrowKey);
+    await topUp();
+    await castVote(proposalKey, { for: {} });
+
+    // The vote is weighed by the lock as it stood when voting started
+    const proposal = await governance.account.proposal.fetch(proposalKey);
+    const escrow = await governance.account.voteEscrow.fetch(voteEscrowKey);
+    assert.equal(escrow.lockStart.toString(), escrowBefore.lockStart.toString());
+    assert.equal(escrow.amount.toString(), escrowBefore.amount.addn(1_000).toString());
+
+    const voteRecord = await voteRecordAddress(proposalKey, authority.publicKey);
+    const record = await governance.account.voteRecord.fetch(voteRecord);
+    const expected = escrowBefore.amount
+      .mul(escrowBefore.lockEnd.sub(proposal.votingStartsAt))
+      .div(new anchor.BN(maxLockDuration));
+    assert.equal(record.weight.toString(), expected.toString());
+
+    // Changing the vote after another extension keeps the same weight
+    await topUp();
+    await governance.methods
+      .changeVote({ against: {} })
+      .accounts({
+        dao: daoKey,
+        proposal: proposalKey,
+        voter: authority.publicKey,
+        voteRecord,
+        voteEscrow: voteEscrowKey,
+      })
+      .signers([authority])
+      .rpc();
+    const changed = await governance.account.voteRecord.fetch(voteRecord);
+    assert.equal(changed.weight.toString(), expected.toString());
+  });
+
+  it("Counts committed votes only when revealed for the same proposal and voter", async () => {
+    const revealPeriod = 3;
+    const weight = new anchor.BN(1_000);
+    const salt = Buffer.from(Array.from({ length: 32 }, () => Math.floor(Math.random() * 256)));
+
+    // keccak(proposal || voter || vote || weight || salt), with For encoded as variant 0
+    const commitmentFor = (proposalKey: PublicKey, vote: number) =>
+      Array.from(
+        Buffer.from(
+          keccak_256.arrayBuffer(
+            Buffer.concat([
+              proposalKey.toBuffer(),
+              authority.publicKey.toBuffer(),
+              Buffer.from([vote]),
+              weight.toArrayLike(Buffer, "le", 8),
+              salt,
+            ])
+          )
+        )
+      );
+
+    const commitVote = async (proposalKey: PublicKey, commitment: number[]) =>
+      governance.methods
+        .commitVote(commitment)
+        .accounts({
+          dao: daoKey,
+          proposal: proposalKey,
+          voter: authority.publicKey,
+          voteRecord: await voteRecordAddress(proposalKey, authority.publicKey),
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([authority])
+        .rpc();
+
+    const revealVote = async (proposalKey: PublicKey, vote: any) =>
+      governance.methods
+        .revealVote(vote, weight, Array.from(salt))
+        .accounts({
+          dao: daoKey,
+          proposal: proposalKey,
+          voter: authority.publicKey,
+          voteRecord: await voteRecordAddress(proposalKey, authority.publicKey),
+          voteEscrow: voteEscrowKey,
+        })
+        .signers([authority])
+        .rpc();
+
+    // Proposals copy the reveal period when they are created
+    await updateDaoSettings({ revealPeriod: new anchor.BN(revealPeriod) });
+    let revealed: PublicKey;
+    let replayed: PublicKey;
+    try {
+      revealed = await createProposal();
+      replayed = await createProposal();
+    } finally {
+      await updateDaoSettings({ revealPeriod: new anchor.BN(0) });
+    }
+    for (const proposalKey of [revealed, replayed]) {
+      await addAction(proposalKey, treasuryTransfer(authority.publicKey, 1));
+      await signOff(proposalKey);
+    }
+
+    // The second proposal receives a copy of the first proposal's commitment
+    const commitment = commitmentFor(revealed, 0);
+    await commitVote(revealed, commitment);
+    await commitVote(replayed, commitment);
+
+    const record = await governance.account.voteRecord.fetch(await voteRecordAddress(revealed, authority.publicKey));
+    assert.isFalse(record.hasVoted);
+    assert.deepEqual(record.commitment, commitment);
+    assert.equal((await governance.account.proposal.fetch(revealed)).forVotes.toString(), "0");
+
+    await sleep((votingPeriod.toNumber() + 1) * 1000);
+
+    try {
+      await revealVote(revealed, { against: {} });
+      assert.fail("Expected a reveal with a different vote to be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidReveal");
+    }
+    try {
+      await revealVote(replayed, { for: {} });
+      assert.fail("Expected a commitment copied from another proposal to be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidReveal");
+    }
+
+    await revealVote(revealed, { for: {} });
+    assert.equal((await governance.account.proposal.fetch(revealed)).forVotes.toString(), weight.toString());
+
+    // Finalizing waits out the reveal period, and unrevealed commitments count for nothing
+    try {
+      await governance.methods.finalizeProposal().accounts({ dao: daoKey, proposal: replayed }).rpc();
+      assert.fail("Expected finalization to wait for the reveal period");
+    } catch (err) {
+      assert.include(err.toString(), "RevealPeriodNotEnded");
+    }
+    await sleep(revealPeriod * 1000);
+
+    await governance.methods.finalizeProposal().accounts({ dao: daoKey, proposal: revealed }).rpc();
+    await governance.methods.finalizeProposal().accounts({ dao: daoKey, proposal: replayed }).rpc();
+    assert.deepEqual((await governance.account.proposal.fetch(revealed)).state, { succeeded: {} });
+
+    const unrevealed = await governance.account.proposal.fetch(replayed);
+    assert.deepEqual(unrevealed.state, { defeated: {} });
+    assert.equal(unrevealed.forVotes.toString(), "0");
+  });
+
+  it("Pays vote rewards, sweeps the unclaimed rest and closes rewarded proposals", async () => {
+    const rewards = 1_000_000;
+    const rewardPoolAddress = async (proposalKey: PublicKey) =>
+      (await PublicKey.findProgramAddress([Buffer.from("miya_reward_pool"), proposalKey.toBuffer()], governance.programId))[0];
+
+    const closeVoteRecord = async (proposalKey: PublicKey) =>
+      governance.methods
+        .closeVoteRecord()
+        .accounts({
+          proposal: proposalKey,
+          rewardPool: await rewardPoolAddress(proposalKey),
+          voteRecord: await voteRecordAddress(proposalKey, authority.publicKey),
+          voter: authority.publicKey,
+        })
+        .signers([authority])
+        .rpc();
+
+    const claimVoteReward = async (proposalKey: PublicKey) =>
+      governance.methods
+        .claimVoteReward()
+        .accounts({
+          proposal: proposalKey,
+          rewardPool: await rewardPoolAddress(proposalKey),
+          voteRecord: await voteRecordAddress(proposalKey, authority.publicKey),
+          voter: authority.publicKey,
+        })
+        .signers([authority])
+        .rpc();
+
+    // Two finished votes to reward, one claimed and one swept
+    const claimed = await createProposal();
+    const swept = await createProposal();
+    const claimedAction = await addAction(claimed, treasuryTransfer(authority.publicKey, 1));
+    await addAction(swept, treasuryTransfer(authority.publicKey, 1));
+    for (const proposalKey of [claimed, swept]) {
+      await signOff(proposalKey);
+      await castVote(proposalKey, { for: {} });
+    }
+    await finalizeAndQueue(claimed);
+    await governance.methods.finalizeProposal().accounts({ dao: daoKey, proposal: swept }).rpc();
+
+    await passProposal([
+      { fundVoteRewards: { proposal: claimed, amount: new anchor.BN(rewards) } },
+      { fundVoteRewards: { proposal: swept, amount: new anchor.BN(rewards) } },
+    ]);
+
+    // The only voter holds all of the counted weight
+    const voterBefore = await provider.connection.getBalance(authority.publicKey);
+    await claimVoteReward(claimed);
+    assert.equal(await provider.connection.getBalance(authority.publicKey), voterBefore + rewards);
+
+    try {
+      await passProposal([{ fundVoteRewards: { proposal: claimed, amount: new anchor.BN(rewards) } }]);
+      assert.fail("Expected a top-up after claims to be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "RewardClaimsStarted");
+    }
+
+    // The vote record carries the right to the reward, so it stays until the reward is claimed or swept
+    try {
+      await closeVoteRecord(swept);
+      assert.fail("Expected the vote record to stay open with an unclaimed reward");
+    } catch (err) {
+      assert.include(err.toString(), "RewardsUnclaimed");
+    }
+
+    const sweptPool = await rewardPoolAddress(swept);
+    const treasuryBefore = await provider.connection.getBalance(treasuryKey);
+    await passProposal([{ sweepVoteRewards: { proposal: swept } }]);
+    assert.equal(await provider.connection.getBalance(treasuryKey), treasuryBefore + rewards);
+
+    const pool = await governance.account.rewardPool.fetch(sweptPool);
+    assert.isTrue(pool.swept);
+    const poolInfo = await provider.connection.getAccountInfo(sweptPool);
+    assert.equal(
+      poolInfo.lamports,
+      await provider.connection.getMinimumBalanceForRentExemption(poolInfo.data.length)
+    );
+
+    try {
+      await claimVoteReward(swept);
+      assert.fail("Expected a claim after the sweep to be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "RewardsSwept");
+    }
+    await closeVoteRecord(swept);
+
+    // A passed proposal nobody executes only becomes closable once its execution window runs out
+    try {
+      await governance.methods.expireProposal().accounts({ proposal: swept }).rpc();
+      assert.fail("Expected the proposal to stay executable");
+    } catch (err) {
+      assert.include(err.toString(), "ProposalNotExpired");
+    }
+
+    // Closing a fully claimed proposal returns its reward pool's rent to the treasury
+    await executeAction(claimed, claimedAction);
+    await governance.methods
+      .closeActionRecord()
+      .accounts({ proposal: claimed, actionRecord: claimedAction, proposer: authority.publicKey })
+      .signers([authority])
+      .rpc();
+    const claimedPool = await rewardPoolAddress(claimed);
+    const poolRent = await provider.connection.getBalance(claimedPool);
+    const treasuryBeforeClose = await provider.connection.getBalance(treasuryKey);
+    await closeProposal(claimed);
+    assert.isNull(await provider.connection.getAccountInfo(claimedPool));
+    assert.isNull(await provider.connection.getAccountInfo(claimed));
+    assert.equal(await provider.connection.getBalance(treasuryKey), treasuryBeforeClose + poolRent);
+  });
+
+  describe("protocol administration", () => {
+    // Set up a mixer pool, the bridge and the zk engine under the test authority
+    before(async () => {
+      // Initialize a mixer pool
+      const [mixerConfigKey] = await PublicKey.findProgramAddress([Buffer.from("miya_mixer_config")], mixer.programId);
+      if (!(await provider.connection.getAccountInfo(mixerConfigKey))) {
+        await mixer.methods
+          .initializeMixerConfig()
+          .accounts({ config: mixerConfigKey, authority: provider.wallet.publicKey, systemProgram: SystemProgram.programId })
+          .rpc();
+      }
+      const poolMint = await createMint(provider.connection, authority, authority.publicKey, null, 9);
+      [poolKey] = await PublicKey.findProgramAddress(
+        [Buffer.from("miya_pool"), poolMint.toBuffer(), depositAmount.toArrayLike(Buffer, "le", 8)],
+        mixer.programId
+      );
+      await mixer.methods
+        .initializePool(poolMint, depositAmount, Keypair.generate().publicKey)
+        .accounts({
+          pool: poolKey,
+          authority: authority.publicKey,
+          config: mixerConfigKey,
+          configAuthority: provider.wallet.publicKey,
+          tokenMint: poolMint,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([authority])
+        .rpc();
+
+      // Initialize the bridge with one token pair
+      [bridgeKey] = await PublicKey.findProgramAddress([Buffer.from("miya_bridge")], bridge.programId);
+      await bridge.methods
+        .initializeBridge()
+        .accounts({ bridge: bridgeKey, authority: authority.publicKey, systemProgram: SystemProgram.programId })
+        .signers([authority])
+        .rpc();
+      const chainKeys: PublicKey[] = [];
+      for (const chainId of [1, 2]) {
+        const chainIdBuffer = Buffer.alloc(2);
+        chainIdBuffer.writeUInt16LE(chainId);
+        const [chainKey] = await PublicKey.findProgramAddress(
+          [Buffer.from("miya_chain"), chainIdBuffer],
+          bridge.programId
+        );
+        await bridge.methods
+          .addSupportedChain(chainId, `chain-${chainId}`, bridge.programId)
+          .accounts({
+            bridge: bridgeKey,
+            supportedChain: chainKey,
+            authority: authority.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([authority])
+          .rpc();
+        chainKeys.push(chainKey);
+      }
+      const bridgeMint = await createMint(provider.connection, authority, authority.publicKey, null, 9);
+      const sourceChain = Buffer.alloc(2);
+      sourceChain.writeUInt16LE(1);
+      const targetChain = Buffer.alloc(2);
+      targetChain.writeUInt16LE(2);
+      [tokenPairKey] = await PublicKey.findProgramAddress(
+        [Buffer.from("miya_token_pair"), sourceChain, targetChain, bridgeMint.toBuffer()],
+        bridge.programId
+      );
+      await bridge.methods
+        .registerTokenPair(1, 2, Buffer.from("source-token"), 100)
+        .accounts({
+          bridge: bridgeKey,
+          tokenPair: tokenPairKey,
+          supportedSourceChain: chainKeys[0],
+          supportedTargetChain: chainKeys[1],
+          targetTokenMint: bridgeMint,
+          authority: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([authority])
+        .rpc();
+
+      // Initialize the zk engine
+      [engineKey] = await PublicKey.findProgramAddress([Buffer.from("miya_zkengine")], zkengine.programId);
+      await zkengine.methods
+        .initialize()
+        .accounts({ engine: engineKey, authority: authority.publicKey, systemProgram: SystemProgram.programId })
+        .signers([authority])
+        .rpc();
+    });
+
+    // Two-step authority transfer of the DAO and of each admin account, driven through its own program
+    const authorityTransfers = () => [
+      {
+        name: "DAO",
+        transfer: (current: Keypair, newAuthority: PublicKey) =>
+          governance.methods
+            .transferDaoAuthority(newAuthority)
+            .accounts({ dao: daoKey, authority: current.publicKey })
+            .signers([current])
+            .rpc(),
+        accept: (signer: Keypair) =>
+          governance.methods
+            .acceptDaoAuthority()
+            .accounts({ dao: daoKey, newAuthority: signer.publicKey })
+            .signers([signer])
+            .rpc(),
+        cancel: (current: Keypair) =>
+          governance.methods
+            .cancelDaoAuthorityTransfer()
+            .accounts({ dao: daoKey, authority: current.publicKey })
+            .signers([current])
+            .rpc(),
+        fetch: () => governance.account.dao.fetch(daoKey),
+      },
+      {
+        name: "pool",
+        transfer: (current: Keypair, newAuthority: PublicKey) =>
+          mixer.methods
+            .transferPoolAuthority(newAuthority)
+            .accounts({ pool: poolKey, authority: current.publicKey })
+            .signers([current])
+            .rpc(),
+        accept: (signer: Keypair) =>
+          mixer.methods
+            .acceptPoolAuthority()
+            .accounts({ pool: poolKey, newAuthority: signer.publicKey })
+            .signers([signer])
+            .rpc(),
+        cancel: (current: Keypair) =>
+          mixer.methods
+            .cancelPoolAuthorityTransfer()
+            .accounts({ pool: poolKey, authority: current.publicKey })
+            .signers([current])
+            .rpc(),
+        fetch: () => mixer.account.pool.fetch(poolKey),
+      },
+      {
+        name: "bridge",
+        transfer: (current: Keypair, newAuthority: PublicKey) =>
+          bridge.methods
+            .transferBridgeAuthority(newAuthority)
+            .accounts({ bridge: bridgeKey, authority: current.publicKey })
+            .signers([current])
+            .rpc(),
+        accept: (signer: Keypair) =>
+          bridge.methods
+            .acceptBridgeAuthority()
+            .accounts({ bridge: bridgeKey, newAuthority: signer.publicKey })
+            .signers([signer])
+            .rpc(),
+        cancel: (current: Keypair) =>
+          bridge.methods
+            .cancelBridgeAuthorityTransfer()
+            .accounts({ bridge: bridgeKey, authority: current.publicKey })
+            .signers([current])
+            .rpc(),
+        fetch: () => bridge.account.bridge.fetch(bridgeKey),
+      },
+      {
+        name: "engine",
+        transfer: (current: Keypair, newAuthority: PublicKey) =>
+          zkengine.methods
+            .transferEngineAuthority(newAuthority)
+            .accounts({ engine: engineKey, authority: current.publicKey })
+            .signers([current])
+            .rpc(),
+        accept: (signer: Keypair) =>
+          zkengine.methods
+            .acceptEngineAuthority()
+            .accounts({ engine: engineKey, newAuthority: signer.publicKey })
+            .signers([signer])
+            .rpc(),
+        cancel: (current: Keypair) =>
+          zkengine.methods
+            .cancelEngineAuthorityTransfer()
+            .accounts({ engine: engineKey, authority: current.publicKey })
+            .signers([current])
+            .rpc(),
+        fetch: () => zkengine.account.zkEngine.fetch(engineKey),
+      },
+    ];
+
+    it("Moves authority only once the pending authority accepts", async () => {
+      const nominee = Keypair.generate();
+      const stranger = Keypair.generate();
+
+      for (const { name, transfer, accept, cancel, fetch } of authorityTransfers()) {
+        // A signer other than the pending authority cannot accept
+        await transfer(authority, nominee.publicKey);
+        try {
+          await accept(stranger);
+          assert.fail(`Expected the ${name} transfer to reject a non-pending signer`);
+        } catch (err) {
+          assert.include(err.toString(), "Unauthorized");
+        }
+        let account = await fetch();
+        assert.equal(account.authority.toString(), authority.publicKey.toString());
+        assert.equal(account.pendingAuthority.toString(), nominee.publicKey.toString());
+
+        // Canceling clears the pending authority, so it can no longer accept
+        await cancel(authority);
+        assert.isNull((await fetch()).pendingAuthority);
+        try {
+          await accept(nominee);
+          assert.fail(`Expected the canceled ${name} transfer to be rejected`);
+        } catch (err) {
+          assert.include(err.toString(), "NoPendingAuthority");
+        }
+
+        // Accepting completes the transfer; hand the authority back the same way
+        await transfer(authority, nominee.publicKey);
+        await accept(nominee);
+        account = await fetch();
+        assert.equal(account.authority.toString(), nominee.publicKey.toString());
+        assert.isNull(account.pendingAuthority);
+        await transfer(nominee, authority.publicKey);
+        await accept(authority);
+        assert.equal((await fetch()).authority.toString(), authority.publicKey.toString());
+      }
+    });
+
+    it("Takes over protocol administration through a proposal", async () => {
+      // Propose the treasury as authority of the pool, the bridge and the zk engine
+      for (const { name, transfer } of authorityTransfers()) {
+        if (name !== "DAO") {
+          await transfer(authority, treasuryKey);
+        }
+      }
+      await passProposal([
+        { acceptPoolAuthority: { pool: poolKey } },
+        { acceptBridgeAuthority: {} },
+        { acceptEngineAuthority: {} },
+      ]);
+
+      const pool = await mixer.account.pool.fetch(poolKey);
+      assert.equal(pool.authority.toString(), treasuryKey.toString());
+      assert.isNull(pool.pendingAuthority);
+
+      const bridgeAccount = await bridge.account.bridge.fetch(bridgeKey);
+      assert.equal(bridgeAccount.authority.toString(), treasuryKey.toString());
+      assert.isNull(bridgeAccount.pendingAuthority);
+
+      const engine = await zkengine.account.zkEngine.fetch(engineKey);
+      assert.equal(engine.authority.toString(), treasuryKey.toString());
+      assert.isNull(engine.pendingAuthority);
+    });
+
+    it("Pauses a pool, sets a bridge fee and registers a verifier through a proposal", async () => {
+      const verifierKey = Keypair.generate().publicKey;
+      const proposalKey = await passProposal([
+        { pausePool: { pool: poolKey } },
+        { setBridgeFee: { tokenPair: tokenPairKey, feePercentage: 250 } },
+        { registerVerifier: { verifierKey, verifierType: { groth16: {} } } },
+      ]);
+
+      const proposal = await governance.account.proposal.fetch(proposalKey);
+      assert.deepEqual(proposal.state, { executed: {} });
+
+      const pool = await mixer.account.pool.fetch(poolKey);
+      assert.isFalse(pool.isActive);
+
+      const tokenPair = await bridge.account.tokenPair.fetch(tokenPairKey);
+      assert.equal(tokenPair.feePercentage, 250);
+
+      const [verifierPda] = await PublicKey.findProgramAddress(
+        [Buffer.from("miya_verifier"), verifierKey.toBuffer()],
+        zkengine.programId
+      );
+      const verifier = await zkengine.account.verifier.fetch(verifierPda);
+      assert.equal(verifier.key.toString(), verifierKey.toString());
+      assert.deepEqual(verifier.verifierType, { groth16: {} });
+      assert.isTrue(verifier.isActive);
+    });
+
+    it("Rejects direct admin calls from the previous authority", async () => {
+      try {
+        await mixer.methods
+          .resumePool()
+          .accounts({ pool: poolKey, authority: authority.publicKey })
+          .signers([authority])
+          .rpc();
+        assert.fail("Expected the previous authority to be rejected");
+      } catch (err) {
+        assert.include(err.toString(), "Unauthorized");
+      }
+    });
+
+    describe("anonymous voting", () => {
+      const quorumMembers = new anchor.BN(2);
+      const verifierKey = Keypair.generate().publicKey;
+      let verifierPda: PublicKey;
+      let registryKey: PublicKey;
+
+      const commitment = (seed: number) => Array.from(Buffer.from(keccak_256.arrayBuffer(Buffer.from([seed]))));
+
+      const registerVoter = (leaf: number[], signer: Keypair) =>
+        governance.methods
+          .registerVoter(leaf)
+          .accounts({ dao: daoKey, voterRegistry: registryKey, authority: signer.publicKey })
+          .signers([signer])
+          .rpc();
+
+      before(async () => {
+        await passProposal([{ registerVerifier: { verifierKey, verifierType: { groth16: {} } } }]);
+        [verifierPda] = await PublicKey.findProgramAddress(
+          [Buffer.from("miya_verifier"), verifierKey.toBuffer()],
+          zkengine.programId
+        );
+
+        [registryKey] = await PublicKey.findProgramAddress(
+          [Buffer.from("miya_voter_registry"), daoKey.toBuffer()],
+          governance.programId
+        );
+        await governance.methods
+          .initializeVoterRegistry(verifierPda, quorumMembers)
+          .accounts({
+            dao: daoKey,
+            voterRegistry: registryKey,
+            authority: authority.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([authority])
+          .rpc();
+      });
+
+      it("Registers members into the voter tree", async () => {
+        const emptyRegistry = await governance.account.voterRegistry.fetch(registryKey);
+        assert.equal(emptyRegistry.memberCount, 0);
+        assert.equal(emptyRegistry.quorumMembers.toNumber(), quorumMembers.toNumber());
+
+        await registerVoter(commitment(1), authority);
+        const oneMember = await governance.account.voterRegistry.fetch(registryKey);
+        assert.equal(oneMember.memberCount, 1);
+        assert.notDeepEqual(oneMember.root, emptyRegistry.root);
+
+        await registerVoter(commitment(2), authority);
+        const twoMembers = await governance.account.voterRegistry.fetch(registryKey);
+        assert.equal(twoMembers.memberCount, 2);
+        assert.notDeepEqual(twoMembers.root, oneMember.root);
+
+        const outsider = Keypair.generate();
+        try {
+          await registerVoter(commitment(3), outsider);
+          assert.fail("Expected a non-authority registration to be rejected");
+        } catch (err) {
+          assert.include(err.toString(), "Unauthorized");
+        }
+        const registry = await governance.account.voterRegistry.fetch(registryKey);
+        assert.equal(registry.memberCount, 2);
+      });
+
+      it("Snapshots the member quorum and refuses votes while proofs cannot be verified", async () => {
+        await updateDaoSettings({ anonymousVoting: true });
+        try {
+          const proposalKey = await createProposal();
+          await addAction(proposalKey, treasuryTransfer(authority.publicKey, 1_000));
+          await governance.methods
+            .signOffProposal()
+            .accounts({ dao: daoKey, proposal: proposalKey, proposer: authority.publicKey })
+            .remainingAccounts([{ pubkey: registryKey, isSigner: false, isWritable: false }])
+            .signers([authority])
+            .rpc();
+
+          // Later quorum changes don't move the target of a proposal already voting
+          await governance.methods
+            .setVoterQuorum(quorumMembers.addn(1))
+            .accounts({ dao: daoKey, voterRegistry: registryKey, authority: authority.publicKey })
+            .signers([authority])
+            .rpc();
+
+          const registry = await governance.account.voterRegistry.fetch(registryKey);
+          const proposal = await governance.account.proposal.fetch(proposalKey);
+          assert.equal(proposal.memberQuorum.toNumber(), quorumMembers.toNumber());
+          assert.deepEqual(proposal.voterRoot, registry.root);
+
+          const nullifier = commitment(42);
+          const [voteNullifier] = await PublicKey.findProgramAddress(
+            [Buffer.from("miya_vote_nullifier"), proposalKey.toBuffer(), Buffer.from(nullifier)],
+            governance.programId
+          );
+          try {
+            await governance.methods
+              .castAnonymousVote({ for: {} }, nullifier, Buffer.alloc(256, 7))
+              .accounts({
+                dao: daoKey,
+                proposal: proposalKey,
+                voterRegistry: registryKey,
+                voteNullifier,
+                verifier: verifierPda,
+                payer: provider.wallet.publicKey,
+                zkengineProgram: zkengine.programId,
+                systemProgram: SystemProgram.programId,
+              })
+              .rpc();
+            assert.fail("Expected the vote to be refused");
+          } catch (err) {
+            // The zk engine has no proving system yet and says so instead of rejecting the proof
+            assert.include(err.toString(), "ProofVerificationUnavailable");
+          }
+
+          assert.isNull(await provider.connection.getAccountInfo(voteNullifier));
+          const tallied = await governance.account.proposal.fetch(proposalKey);
+          assert.equal(tallied.forVotes.toString(), "0");
+        } finally {
+          await updateDaoSettings({ anonymousVoting: false });
+        }
+      });
+
+      // Needs a membership proof the zk engine accepts, which no proving system produces yet
+      it.skip("Rejects a second vote with the same nullifier", async () => {
+        // Cast an anonymous vote, then expect the same nullifier to fail creating its PDA
+      });
+    });
+  });
+});
@@ -1,9 +1,10 @@
 import * as anchor from "@project-serum/anchor";
 import { Program } from "@project-serum/anchor";
 import { PublicKey, Keypair, SystemProgram } from "@solana/web3.js";
-import { TOKEN_PROGRAM_ID, createMint, createAssociatedTokenAccount, mintTo } from "@solana/spl-token";
+import { TOKEN_PROGRAM_ID, createMint, createAccount, createAssociatedTokenAccount, mintTo } from "@solana/spl-token";
 import { assert } from "chai";
 import { MiyaMixer } from "../target/types/miya_mixer";
+import { MiyaZkengine } from "../target/types/miya_zkengine";
 
 describe("miya_mixer", () => {
   // Configure the client to use the local cluster
@@ -11,6 +12,7 @@ describe("miya_mixer", () => {
   anchor.setProvider(provider);
 
   const program = anchor.workspace.MiyaMixer as Program<MiyaMixer>;
+  const zkengine = anchor.workspace.MiyaZkengine as Program<MiyaZkengine>;
   const authority = Keypair.generate();
   const user = Keypair.generate();
   let tokenMint: PublicKey;
@@ -18,14 +20,65 @@ describe("miya_mixer", () => {
   let poolTokenAccount: PublicKey;
   let poolKey: PublicKey;
   let poolBump: number;
+  let configKey: PublicKey;
+  let verifierPda: PublicKey;
   
   const depositAmount = new anchor.BN(1_000_000_000); // 1 token with 9 decimals
   
+  const nullifierAddress = async (pool: PublicKey, nullifier: number[]) =>
+    (
+      await PublicKey.findProgramAddress(
+        [Buffer.from("miya_nullifier"), pool.toBuffer(), Buffer.from(nullifier)],
+        program.programId
+      )
+    )[0];
+  
   before(async () => {
     // Airdrop SOL to authority and user
     await provider.connection.requestAirdrop(authority.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
     await provider.connection.requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
     
+    // The mixer config is a singleton that other suites may already have set up
+    [configKey] = await PublicKey.findProgramAddress([Buffer.from("miya_mixer_config")], program.programId);
+    if (!(await provider.connection.getAccountInfo(configKey))) {
+      await program.methods
+        .initializeMixerConfig()
+        .accounts({ config: configKey, authority: provider.wallet.publicKey, systemProgram: SystemProgram.programId })
+        .rpc();
+    }
+    
+    // Withdrawal proofs go to a Groth16 verifier, registered here unless another suite already did
+    const verifiers = await zkengine.account.verifier.all();
+    const activeVerifier = verifiers.find(
+      (verifier) => verifier.account.isActive && "groth16" in verifier.account.verifierType
+    );
+    if (activeVerifier) {
+      verifierPda = activeVerifier.publicKey;
+    } else {
+      const [engineKey] = await PublicKey.findProgramAddress([Buffer.from("miya_zkengine")], zkengine.programId);
+      if (!(await provider.connection.getAccountInfo(engineKey))) {
+        await zkengine.methods
+          .initialize()
+          .accounts({ engine: engineKey, authority: provider.wallet.publicKey, systemProgram: SystemProgram.programId })
+          .rpc();
+      }
+      const verifierKey = Keypair.generate().publicKey;
+      [verifierPda] = await PublicKey.findProgramAddress(
+        [Buffer.from("miya_verifier"), verifierKey.toBuffer()],
+        zkengine.programId
+      );
+      await zkengine.methods
+        .registerVerifier(verifierKey, { groth16: {} })
+        .accounts({
+          engine: engineKey,
+          verifier: verifierPda,
+          verifierKey,
+          authority: provider.wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    }
+    
     // Create token mint
     tokenMint = await createMint(
       provider.connection,
@@ -76,10 +129,12 @@ describe("miya_mixer", () => {
   it("Initialize pool", async () => {
     // Initialize the pool
     await program.methods
-      .initializePool(tokenMint, depositAmount)
+      .initializePool(tokenMint, depositAmount, verifierPda)
       .accounts({
         pool: poolKey,
         authority: authority.publicKey,
+        config: configKey,
+        configAuthority: provider.wallet.publicKey,
         tokenMint: tokenMint,
         systemProgram: SystemProgram.programId,
       })
@@ -93,6 +148,7 @@ describe("miya_mixer", () => {
     assert.equal(pool.authority.toString(), authority.publicKey.toString());
     assert.equal(pool.tokenMint.toString(), tokenMint.toString());
     assert.equal(pool.depositAmount.toString(), depositAmount.toString());
+    assert.equal(pool.verifier.toString(), verifierPda.toString());
     assert.equal(pool.totalDeposits.toString(), "0");
     assert.isTrue(pool.isActive);
     assert.equal(pool.bump, poolBump);
@@ -111,6 +167,7 @@ describe("miya_mixer", () => {
         signer: user.publicKey,
         userTokenAccount: userTokenAccount,
         poolTokenAccount: poolTokenAccount,
+        tokenMint: tokenMint,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
       .signers([user])
@@ -121,6 +178,17 @@ describe("miya_mixer", () => {
     
     // Verify pool data
     assert.equal(pool.totalDeposits.toString(), "1");
+    assert.equal(pool.nextLeafIndex.toString(), "1");
+    assert.equal(pool.anonymitySet.toString(), "1");
+  });
+
+  it("Reports pool stats", async () => {
+    const stats = await program.methods.getPoolStats().accounts({ pool: poolKey }).view();
+    
+    assert.equal(stats.totalDeposits.toString(), "1");
+    assert.equal(stats.totalWithdrawals.toString(), "0");
+    assert.equal(stats.nextLeafIndex.toString(), "1");
+    assert.equal(stats.anonymitySet.toString(), "1");
   });
 
   it("Pause pool", async () => {
@@ -159,6 +227,482 @@ describe("miya_mixer", () => {
     assert.isTrue(pool.isActive);
   });
 
+  it("Deposit to a native SOL pool", async () => {
+    const nativeDepositAmount = new anchor.BN(anchor.web3.LAMPORTS_PER_SOL / 10);
+    
+    // Native pools are keyed by the default pubkey in place of a mint
+    const [nativePoolKey] = await PublicKey.findProgramAddress(
+      [
+        Buffer.from("miya_pool"),
+        PublicKey.default.toBuffer(),
+        nativeDepositAmount.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    const [vaultKey] = await PublicKey.findProgramAddress(
+      [Buffer.from("miya_sol_vault"), nativePoolKey.toBuffer()],
+      program.programId
+    );
+    
+    await program.methods
+      .initializeNativePool(nativeDepositAmount, verifierPda)
+      .accounts({
+        pool: nativePoolKey,
+        vault: vaultKey,
+        authority: authority.publicKey,
+        config: configKey,
+        configAuthority: provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([authority])
+      .rpc();
+    
+    const vaultBalanceBefore = await provider.connection.getBalance(vaultKey);
+    
+    const commitment = Array.from({ length: 32 }, () => Math.floor(Math.random() * 256));
+    const nullifierHash = Array.from({ length: 32 }, () => Math.floor(Math.random() * 256));
+    await program.methods
+      .depositNative(commitment, nullifierHash)
+      .accounts({
+        pool: nativePoolKey,
+        vault: vaultKey,
+        signer: user.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user])
+      .rpc();
+    
+    // Verify the lamports landed in the vault
+    const vaultBalanceAfter = await provider.connection.getBalance(vaultKey);
+    assert.equal(vaultBalanceAfter - vaultBalanceBefore, nativeDepositAmount.toNumber());
+    
+    const pool = await program.account.pool.fetch(nativePoolKey);
+    assert.equal(pool.tokenMint.toString(), PublicKey.default.toString());
+    assert.equal(pool.totalDeposits.toString(), "1");
+  });
+
+  it("Rejects a native withdrawal whose proof does not verify", async () => {
+    const nativeDepositAmount = new anchor.BN(anchor.web3.LAMPORTS_PER_SOL / 10);
+    const [nativePoolKey] = await PublicKey.findProgramAddress(
+      [
+        Buffer.from("miya_pool"),
+        PublicKey.default.toBuffer(),
+        nativeDepositAmount.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    const [vaultKey] = await PublicKey.findProgramAddress(
+      [Buffer.from("miya_sol_vault"), nativePoolKey.toBuffer()],
+      program.programId
+    );
+
+    const recipient = Keypair.generate();
+    const publicInputs = {
+      nullifier: Array.from({ length: 32 }, () => Math.floor(Math.random() * 256)),
+      recipient: recipient.publicKey,
+      relayer: null,
+      fee: new anchor.BN(0),
+      refund: new anchor.BN(0),
+    };
+    const poolNullifier = await nullifierAddress(nativePoolKey, publicInputs.nullifier);
+    const vaultBalance = await provider.connection.getBalance(vaultKey);
+
+    // The zk engine has no proving system to accept the proof, so the note stays unspent
+    // and the vault untouched
+    try {
+      await program.methods
+        .withdrawNative(Buffer.from([]), publicInputs)
+        .accounts({
+          pool: nativePoolKey,
+          vault: vaultKey,
+          poolNullifier,
+          recipient: recipient.publicKey,
+          relayerAccount: null,
+          relayerRegistration: null,
+          verifier: verifierPda,
+          zkengineProgram: zkengine.programId,
+          payer: provider.wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      assert.fail("Expected the withdrawal to be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "ProofVerificationUnavailable");
+    }
+    assert.isNull(await provider.connection.getAccountInfo(poolNullifier));
+    assert.equal(await provider.connection.getBalance(vaultKey), vaultBalance);
+    assert.equal(await provider.connection.getBalance(recipient.publicKey), 0);
+  });
+
+  it("Registers a relayer and caps pool relayer fees", async () => {
+    const relayerOwner = Keypair.generate();
+    const signature = await provider.connection.requestAirdrop(relayerOwner.publicKey, 3 * anchor.web3.LAMPORTS_PER_SOL);
+    await provider.connection.confirmTransaction(signature);
+    
+    const [relayerKey] = await PublicKey.findProgramAddress(
+      [Buffer.from("miya_relayer"), relayerOwner.publicKey.toBuffer()],
+      program.programId
+    );
+    
+    const stake = new anchor.BN(anchor.web3.LAMPORTS_PER_SOL);
+    await program.methods
+      .registerRelayer(stake, 100)
+      .accounts({
+        relayer: relayerKey,
+        owner: relayerOwner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([relayerOwner])
+      .rpc();
+    
+    const relayer = await program.account.relayer.fetch(relayerKey);
+    assert.equal(relayer.owner.toString(), relayerOwner.publicKey.toString());
+    assert.equal(relayer.stake.toString(), stake.toString());
+    assert.equal(relayer.feeBps, 100);
+    assert.isTrue(relayer.isActive);
+    
+    await program.methods
+      .setRelayerPolicy(200, true)
+      .accounts({
+        pool: poolKey,
+        authority: authority.publicKey,
+      })
+      .signers([authority])
+      .rpc();
+    
+    const pool = await program.account.pool.fetch(poolKey);
+    assert.equal(pool.maxRelayerFeeBps, 200);
+    assert.isTrue(pool.requireRegisteredRelayer);
+  });
+
+  it("Slashes a relayer's stake through the mixer authority", async () => {
+    const relayerOwner = Keypair.generate();
+    const signature = await provider.connection.requestAirdrop(relayerOwner.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+    await provider.connection.confirmTransaction(signature);
+    
+    const [relayerKey] = await PublicKey.findProgramAddress(
+      [Buffer.from("miya_relayer"), relayerOwner.publicKey.toBuffer()],
+      program.programId
+    );
+    const stake = new anchor.BN(anchor.web3.LAMPORTS_PER_SOL);
+    await program.methods
+      .registerRelayer(stake, 100)
+      .accounts({
+        relayer: relayerKey,
+        owner: relayerOwner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([relayerOwner])
+      .rpc();
+    
+    const recipient = Keypair.generate();
+    const slashRelayer = (amount: anchor.BN, signer: Keypair | null) => {
+      const builder = program.methods
+        .slashRelayer(amount)
+        .accounts({
+          config: configKey,
+          relayer: relayerKey,
+          recipient: recipient.publicKey,
+          authority: signer ? signer.publicKey : provider.wallet.publicKey,
+        });
+      return signer ? builder.signers([signer]).rpc() : builder.rpc();
+    };
+    
+    // Only the mixer authority can slash
+    try {
+      await slashRelayer(stake.divn(2), relayerOwner);
+      assert.fail("Expected the slash to be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "Unauthorized");
+    }
+    
+    const relayerBalanceBefore = await provider.connection.getBalance(relayerKey);
+    await slashRelayer(stake.divn(2), null);
+    
+    const relayer = await program.account.relayer.fetch(relayerKey);
+    assert.equal(relayer.stake.toString(), stake.divn(2).toString());
+    assert.isFalse(relayer.isActive);
+    assert.isTrue(relayer.deactivatedAt.gtn(0));
+    assert.equal(await provider.connection.getBalance(recipient.publicKey), stake.divn(2).toNumber());
+    assert.equal(await provider.connection.getBalance(relayerKey), relayerBalanceBefore - stake.divn(2).toNumber());
+    
+    // Nothing beyond the remaining stake can be taken
+    try {
+      await slashRelayer(stake, null);
+      assert.fail("Expected the slash to be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InsufficientRelayerStake");
+    }
+    
+    // The owner cannot put a relayer whose stake fell below the minimum back to work
+    try {
+      await program.methods
+        .updateRelayer(null, true)
+        .accounts({
+          relayer: relayerKey,
+          owner: relayerOwner.publicKey,
+        })
+        .signers([relayerOwner])
+        .rpc();
+      assert.fail("Expected the re-activation to be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InsufficientRelayerStake");
+    }
+    assert.isFalse((await program.account.relayer.fetch(relayerKey)).isActive);
+  });
+
+  it("Creates and lists denomination pools for a mint", async () => {
+    const factoryMint = await createMint(provider.connection, authority, authority.publicKey, null, 9);
+    const denominations = [100_000_000, 1_000_000_000, 10_000_000_000].map((amount) => new anchor.BN(amount));
+    const poolKeys = await Promise.all(
+      denominations.map(async (amount) =>
+        (
+          await PublicKey.findProgramAddress(
+            [Buffer.from("miya_pool"), factoryMint.toBuffer(), amount.toArrayLike(Buffer, "le", 8)],
+            program.programId
+          )
+        )[0]
+      )
+    );
+    const [registryKey] = await PublicKey.findProgramAddress(
+      [Buffer.from("miya_denominations"), factoryMint.toBuffer()],
+      program.programId
+    );
+    
+    await program.methods
+      .createDenominationPools(denominations, verifierPda)
+      .accounts({
+        config: configKey,
+        registry: registryKey,
+        authority: provider.wallet.publicKey,
+        tokenMint: factoryMint,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(poolKeys.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true })))
+      .rpc();
+    
+    const pools = await program.methods.getDenominationPools().accounts({ registry: registryKey }).view();
+    assert.equal(pools.length, denominations.length);
+    pools.forEach((entry, index) => {
+      assert.equal(entry.depositAmount.toString(), denominations[index].toString());
+      assert.equal(entry.pool.toString(), poolKeys[index].toString());
+    });
+    
+    const pool = await program.account.pool.fetch(poolKeys[1]);
+    assert.equal(pool.depositAmount.toString(), denominations[1].toString());
+    assert.isTrue(pool.isActive);
+  });
+
+  it("Creates a denomination pool whose address was funded in advance", async () => {
+    const factoryMint = await createMint(provider.connection, authority, authority.publicKey, null, 9);
+    const denomination = new anchor.BN(500_000_000);
+    const [poolKey] = await PublicKey.findProgramAddress(
+      [Buffer.from("miya_pool"), factoryMint.toBuffer(), denomination.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [registryKey] = await PublicKey.findProgramAddress(
+      [Buffer.from("miya_denominations"), factoryMint.toBuffer()],
+      program.programId
+    );
+    
+    // Anyone can send lamports to the pool address before it exists
+    const prefunded = await provider.connection.getMinimumBalanceForRentExemption(0);
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        SystemProgram.transfer({ fromPubkey: provider.wallet.publicKey, toPubkey: poolKey, lamports: prefunded })
+      )
+    );
+    
+    await program.methods
+      .createDenominationPools([denomination], verifierPda)
+      .accounts({
+        config: configKey,
+        registry: registryKey,
+        authority: provider.wallet.publicKey,
+        tokenMint: factoryMint,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts([{ pubkey: poolKey, isSigner: false, isWritable: true }])
+      .rpc();
+    
+    const pool = await program.account.pool.fetch(poolKey);
+    assert.equal(pool.depositAmount.toString(), denomination.toString());
+    const poolInfo = await provider.connection.getAccountInfo(poolKey);
+    assert.equal(poolInfo.owner.toString(), program.programId.toString());
+    assert.equal(
+      poolInfo.lamports,
+      await provider.connection.getMinimumBalanceForRentExemption(poolInfo.data.length)
+    );
+  });
+
+  it("Rejects a pool created without the mixer authority", async () => {
+    const otherMint = await createMint(provider.connection, authority, authority.publicKey, null, 9);
+    const [otherPoolKey] = await PublicKey.findProgramAddress(
+      [Buffer.from("miya_pool"), otherMint.toBuffer(), depositAmount.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    
+    try {
+      await program.methods
+        .initializePool(otherMint, depositAmount, verifierPda)
+        .accounts({
+          pool: otherPoolKey,
+          authority: authority.publicKey,
+          config: configKey,
+          configAuthority: authority.publicKey,
+          tokenMint: otherMint,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([authority])
+        .rpc();
+      assert.fail("Expected the pool to be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "Unauthorized");
+    }
+  });
+
+  it("Rejects a withdrawal fee without a relayer", async () => {
+    const publicInputs = {
+      nullifier: Array.from({ length: 32 }, () => Math.floor(Math.random() * 256)),
+      recipient: user.publicKey,
+      relayer: null,
+      fee: new anchor.BN(1),
+      refund: new anchor.BN(0),
+    };
+    
+    try {
+      await program.methods
+        .withdraw(Buffer.from([]), publicInputs)
+        .accounts({
+          pool: poolKey,
+          poolNullifier: await nullifierAddress(poolKey, publicInputs.nullifier),
+          poolTokenAccount: poolTokenAccount,
+          recipientTokenAccount: userTokenAccount,
+          recipient: user.publicKey,
+          relayerTokenAccount: null,
+          relayerAccount: null,
+          relayerRegistration: null,
+          tokenMint: tokenMint,
+          verifier: verifierPda,
+          zkengineProgram: zkengine.programId,
+          payer: provider.wallet.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      assert.fail("Expected the withdrawal to be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "RelayerFeeMismatch");
+    }
+  });
+
+  it("Rejects a withdrawal to a token account the recipient does not own", async () => {
+    const otherTokenAccount = await createAssociatedTokenAccount(
+      provider.connection,
+      authority,
+      tokenMint,
+      authority.publicKey
+    );
+    const publicInputs = {
+      nullifier: Array.from({ length: 32 }, () => Math.floor(Math.random() * 256)),
+      recipient: user.publicKey,
+      relayer: null,
+      fee: new anchor.BN(0),
+      refund: new anchor.BN(0),
+    };
+    
+    try {
+      await program.methods
+        .withdraw(Buffer.from([]), publicInputs)
+        .accounts({
+          pool: poolKey,
+          poolNullifier: await nullifierAddress(poolKey, publicInputs.nullifier),
+          poolTokenAccount: poolTokenAccount,
+          recipientTokenAccount: otherTokenAccount,
+          recipient: user.publicKey,
+          relayerTokenAccount: null,
+          relayerAccount: null,
+          relayerRegistration: null,
+          tokenMint: tokenMint,
+          verifier: verifierPda,
+          zkengineProgram: zkengine.programId,
+          payer: provider.wallet.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      assert.fail("Expected the withdrawal to be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidRecipientAccount");
+    }
+  });
+
+  it("Rejects a withdrawal checked by a verifier other than the pool's", async () => {
+    const publicInputs = {
+      nullifier: Array.from({ length: 32 }, () => Math.floor(Math.random() * 256)),
+      recipient: user.publicKey,
+      relayer: null,
+      fee: new anchor.BN(0),
+      refund: new anchor.BN(0),
+    };
+    
+    try {
+      await program.methods
+        .withdraw(Buffer.from([]), publicInputs)
+        .accounts({
+          pool: poolKey,
+          poolNullifier: await nullifierAddress(poolKey, publicInputs.nullifier),
+          poolTokenAccount: poolTokenAccount,
+          recipientTokenAccount: userTokenAccount,
+          recipient: user.publicKey,
+          relayerTokenAccount: null,
+          relayerAccount: null,
+          relayerRegistration: null,
+          tokenMint: tokenMint,
+          verifier: configKey,
+          zkengineProgram: zkengine.programId,
+          payer: provider.wallet.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      assert.fail("Expected the withdrawal to be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "InvalidVerifier");
+    }
+  });
+
+  it("Rejects a deposit into a pool-owned account other than the pool's vault", async () => {
+    // Same owner and mint as the vault, but not the pool's associated token account
+    const strayTokenAccount = await createAccount(
+      provider.connection,
+      authority,
+      tokenMint,
+      poolKey,
+      Keypair.generate()
+    );
+    const commitment = Array.from({ length: 32 }, () => Math.floor(Math.random() * 256));
+    const nullifierHash = Array.from({ length: 32 }, () => Math.floor(Math.random() * 256));
+    
+    try {
+      await program.methods
+        .deposit(commitment, nullifierHash)
+        .accounts({
+          pool: poolKey,
+          signer: user.publicKey,
+          userTokenAccount: userTokenAccount,
+          poolTokenAccount: strayTokenAccount,
+          tokenMint: tokenMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+      assert.fail("Expected the deposit to be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "ConstraintAssociated");
+    }
+  });
+
   // In a real-world scenario, we would also test withdraw functionality
   // However, that requires generating a valid zero-knowledge proof which
   // is beyond the scope of this prototype test
@@ -0,0 +1,173 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import { PublicKey, Keypair, SystemProgram } from "@solana/web3.js";
+import { TOKEN_PROGRAM_ID, createMint, createAssociatedTokenAccount, mintTo, getAccount } from "@solana/spl-token";
+import { assert } from "chai";
+import { MiyaMixer } from "../target/types/miya_mixer";
+import { MiyaZkengine } from "../target/types/miya_zkengine";
+
+describe("shielded pool", () => {
+  // Configure the client to use the local cluster
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const mixer = anchor.workspace.MiyaMixer as Program<MiyaMixer>;
+  const zkengine = anchor.workspace.MiyaZkengine as Program<MiyaZkengine>;
+  const authority = Keypair.generate();
+  const user = Keypair.generate();
+
+  const depositAmount = new anchor.BN(2_500_000_000);
+
+  let tokenMint: PublicKey;
+  let userTokenAccount: PublicKey;
+  let vault: PublicKey;
+  let shieldedPoolKey: PublicKey;
+  let verifierPda: PublicKey;
+
+  const randomBytes = () => Array.from({ length: 32 }, () => Math.floor(Math.random() * 256));
+
+  const nullifierAddress = (nullifier: number[]) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("miya_shielded_nullifier"), shieldedPoolKey.toBuffer(), Buffer.from(nullifier)],
+      mixer.programId
+    )[0];
+
+  const deposit = (root: number[], inputNullifiers: number[][]) =>
+    mixer.methods
+      .transact(Buffer.from([]), {
+        root,
+        inputNullifiers,
+        outputCommitments: [randomBytes(), randomBytes()],
+        extAmount: depositAmount,
+        fee: new anchor.BN(0),
+        recipient: user.publicKey,
+        relayer: null,
+      })
+      .accounts({
+        shieldedPool: shieldedPoolKey,
+        vault,
+        inputNullifier0: nullifierAddress(inputNullifiers[0]),
+        inputNullifier1: nullifierAddress(inputNullifiers[1]),
+        depositorTokenAccount: userTokenAccount,
+        recipientTokenAccount: null,
+        relayerTokenAccount: null,
+        tokenMint,
+        verifier: verifierPda,
+        payer: user.publicKey,
+        zkengineProgram: zkengine.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user])
+      .rpc();
+
+  before(async () => {
+    // Airdrop SOL to authority and user
+    for (const keypair of [authority, user]) {
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(keypair.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL)
+      );
+    }
+
+    // The mixer config and zk engine are singletons that other suites may already have set up
+    const [configKey] = PublicKey.findProgramAddressSync([Buffer.from("miya_mixer_config")], mixer.programId);
+    if (!(await provider.connection.getAccountInfo(configKey))) {
+      await mixer.methods
+        .initializeMixerConfig()
+        .accounts({ config: configKey, authority: provider.wallet.publicKey, systemProgram: SystemProgram.programId })
+        .rpc();
+    }
+
+    // Shielded pools only accept Groth16 verifiers
+    const verifiers = await zkengine.account.verifier.all();
+    const activeVerifier = verifiers.find(
+      (verifier) => verifier.account.isActive && "groth16" in verifier.account.verifierType
+    );
+    if (activeVerifier) {
+      verifierPda = activeVerifier.publicKey;
+    } else {
+      const [engineKey] = PublicKey.findProgramAddressSync([Buffer.from("miya_zkengine")], zkengine.programId);
+      if (!(await provider.connection.getAccountInfo(engineKey))) {
+        await zkengine.methods
+          .initialize()
+          .accounts({ engine: engineKey, authority: provider.wallet.publicKey, systemProgram: SystemProgram.programId })
+          .rpc();
+      }
+      const verifierKey = Keypair.generate().publicKey;
+      [verifierPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("miya_verifier"), verifierKey.toBuffer()],
+        zkengine.programId
+      );
+      await zkengine.methods
+        .registerVerifier(verifierKey, { groth16: {} })
+        .accounts({
+          engine: engineKey,
+          verifier: verifierPda,
+          verifierKey,
+          authority: provider.wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    }
+
+    tokenMint = await createMint(provider.connection, authority, authority.publicKey, null, 9);
+    userTokenAccount = await createAssociatedTokenAccount(provider.connection, user, tokenMint, user.publicKey);
+    await mintTo(provider.connection, authority, tokenMint, userTokenAccount, authority, depositAmount.toNumber());
+
+    [shieldedPoolKey] = PublicKey.findProgramAddressSync(
+      [Buffer.from("miya_shielded_pool"), tokenMint.toBuffer()],
+      mixer.programId
+    );
+    vault = await createAssociatedTokenAccount(provider.connection, authority, tokenMint, shieldedPoolKey, true);
+
+    await mixer.methods
+      .initializeShieldedPool(verifierPda)
+      .accounts({
+        shieldedPool: shieldedPoolKey,
+        authority: authority.publicKey,
+        config: configKey,
+        configAuthority: provider.wallet.publicKey,
+        tokenMint,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([authority])
+      .rpc();
+  });
+
+  it("Refuses a deposit while its proof cannot be verified", async () => {
+    const poolBefore = await mixer.account.shieldedPool.fetch(shieldedPoolKey);
+    const root = poolBefore.roots[poolBefore.currentRootIndex];
+    const inputNullifiers = [randomBytes(), randomBytes()];
+
+    try {
+      await deposit(root, inputNullifiers);
+      assert.fail("Expected the deposit to be refused");
+    } catch (err) {
+      assert.include(err.toString(), "ProofVerificationUnavailable");
+    }
+
+    // Nothing moved and neither input was marked as spent
+    const pool = await mixer.account.shieldedPool.fetch(shieldedPoolKey);
+    assert.equal(pool.nextLeafIndex.toString(), "0");
+    assert.equal(pool.totalValue.toString(), "0");
+    assert.deepEqual(pool.roots[pool.currentRootIndex], root);
+
+    const vaultAccount = await getAccount(provider.connection, vault);
+    assert.equal(vaultAccount.amount.toString(), "0");
+    const userAccount = await getAccount(provider.connection, userTokenAccount);
+    assert.equal(userAccount.amount.toString(), depositAmount.toString());
+
+    for (const nullifier of inputNullifiers) {
+      assert.isNull(await provider.connection.getAccountInfo(nullifierAddress(nullifier)));
+    }
+  });
+
+  it("Rejects a transaction against an unknown root", async () => {
+    try {
+      await deposit(randomBytes(), [randomBytes(), randomBytes()]);
+      assert.fail("Expected the transaction to be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "UnknownMerkleRoot");
+    }
+  });
+});
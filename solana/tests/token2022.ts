@@ -0,0 +1,291 @@
+import * as anchor from "@project-serum/anchor";
+import { Program } from "@project-serum/anchor";
+import { PublicKey, Keypair, SystemProgram, Transaction, TransactionInstruction } from "@solana/web3.js";
+import {
+  TOKEN_2022_PROGRAM_ID,
+  ExtensionType,
+  getMintLen,
+  createInitializeMintInstruction,
+  createInitializeTransferFeeConfigInstruction,
+  createInitializeTransferHookInstruction,
+  createInitializePermanentDelegateInstruction,
+  createInitializeNonTransferableMintInstruction,
+  createInitializeMintCloseAuthorityInstruction,
+  createInitializeInterestBearingMintInstruction,
+  createAssociatedTokenAccount,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
+import { assert } from "chai";
+import { MiyaMixer } from "../target/types/miya_mixer";
+import { MiyaBridge } from "../target/types/miya_bridge";
+
+describe("token-2022", () => {
+  // Configure the client to use the local cluster
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const mixer = anchor.workspace.MiyaMixer as Program<MiyaMixer>;
+  const bridge = anchor.workspace.MiyaBridge as Program<MiyaBridge>;
+  const authority = Keypair.generate();
+  const user = Keypair.generate();
+
+  const depositAmount = new anchor.BN(1_000_000_000); // 1 token with 9 decimals
+
+  // Mint account size with a ConfidentialTransferMint extension:
+  // base account (165) + account type (1) + TLV header (4) + extension data (65)
+  const confidentialMintLen = 235;
+
+  // Create a Token-2022 mint with the given extension initialized before the mint itself
+  const createExtensionMint = async (
+    mintLen: number,
+    extensionInstructions: (mint: PublicKey) => TransactionInstruction[]
+  ) => {
+    const mint = Keypair.generate();
+    const lamports = await provider.connection.getMinimumBalanceForRentExemption(mintLen);
+    const transaction = new Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: authority.publicKey,
+        newAccountPubkey: mint.publicKey,
+        space: mintLen,
+        lamports,
+        programId: TOKEN_2022_PROGRAM_ID,
+      }),
+      ...extensionInstructions(mint.publicKey),
+      createInitializeMintInstruction(mint.publicKey, 9, authority.publicKey, null, TOKEN_2022_PROGRAM_ID)
+    );
+    await provider.sendAndConfirm(transaction, [authority, mint]);
+    return mint.publicKey;
+  };
+
+  const transferFeeMint = () =>
+    createExtensionMint(getMintLen([ExtensionType.TransferFeeConfig]), (mint) => [
+      createInitializeTransferFeeConfigInstruction(
+        mint,
+        authority.publicKey,
+        authority.publicKey,
+        100,
+        BigInt(1_000_000),
+        TOKEN_2022_PROGRAM_ID
+      ),
+    ]);
+
+  const transferHookMint = () =>
+    createExtensionMint(getMintLen([ExtensionType.TransferHook]), (mint) => [
+      createInitializeTransferHookInstruction(mint, authority.publicKey, mixer.programId, TOKEN_2022_PROGRAM_ID),
+    ]);
+
+  const permanentDelegateMint = () =>
+    createExtensionMint(getMintLen([ExtensionType.PermanentDelegate]), (mint) => [
+      createInitializePermanentDelegateInstruction(mint, authority.publicKey, TOKEN_2022_PROGRAM_ID),
+    ]);
+
+  const closeAuthorityMint = () =>
+    createExtensionMint(getMintLen([ExtensionType.MintCloseAuthority]), (mint) => [
+      createInitializeMintCloseAuthorityInstruction(mint, authority.publicKey, TOKEN_2022_PROGRAM_ID),
+    ]);
+
+  const nonTransferableMint = () =>
+    createExtensionMint(getMintLen([ExtensionType.NonTransferable]), (mint) => [
+      createInitializeNonTransferableMintInstruction(mint, TOKEN_2022_PROGRAM_ID),
+    ]);
+
+  // spl-token has no helper for this one, so encode ConfidentialTransferInstruction::InitializeMint directly
+  const confidentialTransferMint = () =>
+    createExtensionMint(confidentialMintLen, (mint) => [
+      new TransactionInstruction({
+        programId: TOKEN_2022_PROGRAM_ID,
+        keys: [{ pubkey: mint, isSigner: false, isWritable: true }],
+        data: Buffer.concat([
+          Buffer.from([27, 0]), // ConfidentialTransferExtension, InitializeMint
+          authority.publicKey.toBuffer(),
+          Buffer.from([0]), // auto_approve_new_accounts
+          Buffer.alloc(32), // no auditor
+        ]),
+      }),
+    ]);
+
+  const poolAddress = (mint: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("miya_pool"), mint.toBuffer(), depositAmount.toArrayLike(Buffer, "le", 8)],
+      mixer.programId
+    )[0];
+
+  const [configKey] = PublicKey.findProgramAddressSync([Buffer.from("miya_mixer_config")], mixer.programId);
+
+  // None of these pools are withdrawn from, so any verifier will do
+  const verifierKey = Keypair.generate().publicKey;
+
+  const initializePool = (mint: PublicKey) =>
+    mixer.methods
+      .initializePool(mint, depositAmount, verifierKey)
+      .accounts({
+        pool: poolAddress(mint),
+        authority: authority.publicKey,
+        config: configKey,
+        configAuthority: provider.wallet.publicKey,
+        tokenMint: mint,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([authority])
+      .rpc();
+
+  before(async () => {
+    // Airdrop SOL to authority and user
+    for (const keypair of [authority, user]) {
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(keypair.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL)
+      );
+    }
+    
+    // The mixer config is a singleton that other suites may already have set up
+    if (!(await provider.connection.getAccountInfo(configKey))) {
+      await mixer.methods
+        .initializeMixerConfig()
+        .accounts({ config: configKey, authority: provider.wallet.publicKey, systemProgram: SystemProgram.programId })
+        .rpc();
+    }
+  });
+
+  it("Pools and deposits a plain Token-2022 mint", async () => {
+    const mint = await createExtensionMint(getMintLen([]), () => []);
+    await initializePool(mint);
+
+    const poolKey = poolAddress(mint);
+    const userTokenAccount = await createAssociatedTokenAccount(
+      provider.connection,
+      user,
+      mint,
+      user.publicKey,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    const poolTokenAccount = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      authority,
+      mint,
+      poolKey,
+      true,
+      undefined,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    await mintTo(
+      provider.connection,
+      authority,
+      mint,
+      userTokenAccount,
+      authority,
+      depositAmount.toNumber(),
+      [],
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+
+    const commitment = Array.from({ length: 32 }, () => Math.floor(Math.random() * 256));
+    const nullifierHash = Array.from({ length: 32 }, () => Math.floor(Math.random() * 256));
+    await mixer.methods
+      .deposit(commitment, nullifierHash)
+      .accounts({
+        pool: poolKey,
+        signer: user.publicKey,
+        userTokenAccount,
+        poolTokenAccount: poolTokenAccount.address,
+        tokenMint: mint,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([user])
+      .rpc();
+
+    const vault = await provider.connection.getTokenAccountBalance(poolTokenAccount.address);
+    assert.equal(vault.value.amount, depositAmount.toString());
+  });
+
+  it("Pools a mint with an allowed extension", async () => {
+    const mint = await createExtensionMint(getMintLen([ExtensionType.InterestBearingConfig]), (mint) => [
+      createInitializeInterestBearingMintInstruction(mint, authority.publicKey, 500, TOKEN_2022_PROGRAM_ID),
+    ]);
+    await initializePool(mint);
+
+    const pool = await mixer.account.pool.fetch(poolAddress(mint));
+    assert.equal(pool.tokenMint.toString(), mint.toString());
+  });
+
+  for (const [name, createMint] of [
+    ["transfer fee", transferFeeMint],
+    ["transfer hook", transferHookMint],
+    ["confidential transfer", confidentialTransferMint],
+    ["permanent delegate", permanentDelegateMint],
+    ["non-transferable", nonTransferableMint],
+    ["mint close authority", closeAuthorityMint],
+  ] as const) {
+    it(`Rejects a mixer pool for a mint with a ${name} extension`, async () => {
+      const mint = await createMint();
+      try {
+        await initializePool(mint);
+        assert.fail("Expected the pool to be rejected");
+      } catch (err) {
+        assert.include(err.toString(), "UnsupportedMintExtension");
+      }
+    });
+  }
+
+  it("Rejects a bridge token pair for a mint with a transfer fee extension", async () => {
+    const [bridgeKey] = PublicKey.findProgramAddressSync([Buffer.from("miya_bridge")], bridge.programId);
+    const chainKeys = [1, 2].map((chainId) => {
+      const chainIdBuffer = Buffer.alloc(2);
+      chainIdBuffer.writeUInt16LE(chainId);
+      return PublicKey.findProgramAddressSync([Buffer.from("miya_chain"), chainIdBuffer], bridge.programId)[0];
+    });
+
+    // The bridge is a singleton that other suites may already have set up
+    if (!(await provider.connection.getAccountInfo(bridgeKey))) {
+      await bridge.methods
+        .initializeBridge()
+        .accounts({ bridge: bridgeKey, authority: authority.publicKey, systemProgram: SystemProgram.programId })
+        .signers([authority])
+        .rpc();
+      for (const [index, chainId] of [1, 2].entries()) {
+        await bridge.methods
+          .addSupportedChain(chainId, `chain-${chainId}`, bridge.programId)
+          .accounts({
+            bridge: bridgeKey,
+            supportedChain: chainKeys[index],
+            authority: authority.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([authority])
+          .rpc();
+      }
+    }
+
+    const mint = await transferFeeMint();
+    const sourceChain = Buffer.alloc(2);
+    sourceChain.writeUInt16LE(1);
+    const targetChain = Buffer.alloc(2);
+    targetChain.writeUInt16LE(2);
+    const [tokenPairKey] = PublicKey.findProgramAddressSync(
+      [Buffer.from("miya_token_pair"), sourceChain, targetChain, mint.toBuffer()],
+      bridge.programId
+    );
+
+    try {
+      await bridge.methods
+        .registerTokenPair(1, 2, Buffer.from("source-token"), 100)
+        .accounts({
+          bridge: bridgeKey,
+          tokenPair: tokenPairKey,
+          supportedSourceChain: chainKeys[0],
+          supportedTargetChain: chainKeys[1],
+          targetTokenMint: mint,
+          authority: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([authority])
+        .rpc();
+      assert.fail("Expected the token pair to be rejected");
+    } catch (err) {
+      assert.include(err.toString(), "UnsupportedMintExtension");
+    }
+  });
+});